@@ -0,0 +1,161 @@
+use proc_macro2::{Ident, Span, TokenStream, TokenTree};
+use quote::{format_ident, quote};
+use syn::visit_mut::VisitMut;
+use syn::{Attribute, FnArg, Pat, Signature, Type};
+
+/// How an argument reaches every attempt.
+#[derive(Clone, Copy, PartialEq)]
+enum Mode {
+    /// A fresh `.clone()` per attempt
+    Clone,
+    /// A re-borrow of the argument for the duration of the attempt
+    Borrow,
+}
+
+/// `#[retry(clone)]` or `#[retry(borrow)]` on an argument; removed from the signature.
+fn take_mode(attrs: &mut Vec<Attribute>) -> syn::Result<Option<Mode>> {
+    let mut mode = None;
+    let mut result = Ok(());
+    attrs.retain(|attr| {
+        if !attr.path().is_ident("retry") {
+            return true;
+        }
+        let parsed = attr.parse_nested_meta(|meta| {
+            let next = if meta.path.is_ident("clone") {
+                Mode::Clone
+            } else if meta.path.is_ident("borrow") {
+                Mode::Borrow
+            } else {
+                return Err(meta.error("expected `clone` or `borrow`"));
+            };
+            if mode.replace(next).is_some() {
+                return Err(meta.error("conflicting `#[retry]` options for this argument"));
+            }
+            Ok(())
+        });
+        if let Err(e) = parsed {
+            result = Err(e);
+        }
+        false
+    });
+    result.map(|()| mode)
+}
+
+/// Statements handing the arguments of a `#[retry]` function to each attempt.
+#[derive(Default)]
+pub struct Rebind {
+    /// Run once, before the retry loop
+    pub setup: Vec<TokenStream>,
+    /// Run in the closure creating each attempt
+    pub per_attempt: Vec<TokenStream>,
+    /// Run at the start of each attempt's future
+    pub in_attempt: Vec<TokenStream>,
+    /// `self` is rebound as `__self`, so the body has to be rewritten
+    pub rename_self: bool,
+}
+
+impl Rebind {
+    /// Bind `name` for every attempt; `outer` is the argument as the function received it and
+    /// `index` its position, which keeps the hidden bindings of different arguments apart.
+    fn bind(&mut self, index: usize, outer: TokenStream, name: &Ident, mutable: bool, ty: &Type, mode: Option<Mode>) -> syn::Result<()> {
+        let lent = format_ident!("__retry_arg{}", index);
+        match ty {
+            Type::Reference(_) if mode == Some(Mode::Clone) => {
+                return Err(syn::Error::new_spanned(ty, "`#[retry(clone)]` needs an owned argument; references are re-borrowed on every attempt"));
+            }
+            // shared references are `Copy`: every attempt gets the same one
+            Type::Reference(reference) if reference.mutability.is_none() => {}
+            Type::Reference(_) => {
+                self.setup.push(quote! { let #lent = ::asyn_retry_policy::__private::Reborrow::new(#outer); });
+                self.per_attempt.push(quote! { let #lent = &#lent; });
+                self.in_attempt.push(quote! { let mut #lent = #lent.lend(); let #name = &mut **#lent; });
+            }
+            _ if mode == Some(Mode::Borrow) => {
+                self.setup.push(quote! { let #lent = ::asyn_retry_policy::__private::Reborrow::new(#outer); });
+                self.per_attempt.push(quote! { let #lent = &#lent; });
+                self.in_attempt.push(quote! { let mut #lent = #lent.lend(); let #name = &mut *#lent; });
+            }
+            _ => {
+                let mutability = mutable.then(|| quote! { mut });
+                self.per_attempt.push(quote! { let #mutability #name = #outer.clone(); });
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Decide how every argument of `sig` reaches the attempts: owned arguments are cloned and
+/// references re-borrowed, unless overridden with `#[retry(clone)]` or `#[retry(borrow)]`.
+///
+/// Strips those attributes, and `mut` from rebound arguments, from `sig`.
+pub fn rebind(sig: &mut Signature) -> syn::Result<Rebind> {
+    let mut rebind = Rebind::default();
+    for (index, input) in sig.inputs.iter_mut().enumerate() {
+        match input {
+            FnArg::Receiver(receiver) => {
+                let mode = take_mode(&mut receiver.attrs)?;
+                let shared = matches!(&*receiver.ty, Type::Reference(r) if r.mutability.is_none());
+                let mutable = receiver.reference.is_none() && receiver.mutability.take().is_some();
+                let name = Ident::new("__self", Span::call_site());
+                rebind.bind(index, quote! { self }, &name, mutable, &receiver.ty, mode)?;
+                rebind.rename_self = !shared;
+            }
+            FnArg::Typed(pat_type) => {
+                let mode = take_mode(&mut pat_type.attrs)?;
+                let Pat::Ident(pat_ident) = &mut *pat_type.pat else {
+                    if let Some(mode) = mode {
+                        let option = if mode == Mode::Clone { "clone" } else { "borrow" };
+                        return Err(syn::Error::new_spanned(&pat_type.pat, format!("`#[retry({option})]` needs a plain identifier argument")));
+                    }
+                    continue;
+                };
+                let name = pat_ident.ident.clone();
+                let shared = matches!(&*pat_type.ty, Type::Reference(r) if r.mutability.is_none());
+                let mutable = !shared && pat_ident.mutability.take().is_some();
+                rebind.bind(index, quote! { #name }, &name, mutable, &pat_type.ty, mode)?;
+            }
+        }
+    }
+    Ok(rebind)
+}
+
+/// Rewrites `self` to `__self` in a function body, including inside macro invocations but not
+/// inside nested items, which have their own `self`.
+pub struct RenameSelf;
+
+impl RenameSelf {
+    fn tokens(tokens: TokenStream) -> TokenStream {
+        let mut out = Vec::new();
+        let mut iter = tokens.into_iter().peekable();
+        while let Some(token) = iter.next() {
+            out.push(match token {
+                // `self::path` names the module, not the receiver
+                TokenTree::Ident(ident) if ident == "self" && !matches!(iter.peek(), Some(TokenTree::Punct(p)) if p.as_char() == ':') => {
+                    TokenTree::Ident(Ident::new("__self", ident.span()))
+                }
+                TokenTree::Group(group) => {
+                    let mut renamed = proc_macro2::Group::new(group.delimiter(), Self::tokens(group.stream()));
+                    renamed.set_span(group.span());
+                    TokenTree::Group(renamed)
+                }
+                other => other,
+            });
+        }
+        out.into_iter().collect()
+    }
+}
+
+impl VisitMut for RenameSelf {
+    fn visit_expr_path_mut(&mut self, expr: &mut syn::ExprPath) {
+        if expr.qself.is_none() && expr.path.is_ident("self") {
+            let span = expr.path.segments[0].ident.span();
+            expr.path.segments[0].ident = Ident::new("__self", span);
+        }
+    }
+
+    fn visit_macro_mut(&mut self, mac: &mut syn::Macro) {
+        mac.tokens = Self::tokens(std::mem::take(&mut mac.tokens));
+    }
+
+    fn visit_item_mut(&mut self, _item: &mut syn::Item) {}
+}
@@ -1,37 +1,56 @@
 use proc_macro::TokenStream;
 use quote::quote;
-use syn::{parse_macro_input, ItemFn, Lit, Expr};
+use syn::visit_mut::VisitMut;
+use syn::{parse_macro_input, Block, DeriveInput, Expr, Item, Lit, ReturnType, Signature, Type};
 
-#[proc_macro_attribute]
-pub fn retry(attr: TokenStream, item: TokenStream) -> TokenStream {
-    // Supported attribute forms:
-    // - empty: `#[retry]`
-    // - single integer: `#[retry(3)]`
-    // - named args: `#[retry(attempts = 3, base_delay_ms = 100, max_delay_ms = 5000, backoff_factor = 2.0, jitter = true, rng_seed = 42)]`
+mod args;
+mod retryable;
 
-    let mut attempts: Option<usize> = None;
-    let mut base_delay_ms: Option<u64> = None;
-    let mut max_delay_ms: Option<u64> = None;
-    let mut backoff_factor: Option<f64> = None;
-    let mut jitter_opt: Option<bool> = None;
-    let mut rng_seed: Option<u64> = None;
-    let mut predicate_expr: Option<syn::Expr> = None;
+/// Options of one `#[retry(...)]` attribute.
+#[derive(Clone, Default)]
+struct Options {
+    attempts: Option<usize>,
+    base_delay_ms: Option<u64>,
+    max_delay_ms: Option<u64>,
+    max_elapsed_ms: Option<u64>,
+    backoff_factor: Option<f64>,
+    jitter: Option<bool>,
+    rng_seed: Option<u64>,
+    on_nested: Option<syn::Expr>,
+    predicate: Option<syn::Expr>,
+    fallback: Option<syn::Expr>,
+    /// Base policy (a const, static or call) the other options override
+    policy: Option<syn::Expr>,
+    /// Leave the item as it is (`#[retry(skip)]` on a method of a `#[retry]` impl)
+    skip: bool,
+    /// Return `RetryError<E>`, reporting shutdown interruptions as `RetryError::ShuttingDown`
+    until_shutdown: bool,
+}
 
-    if !attr.is_empty() {
+impl Options {
+    fn parse(attr: proc_macro2::TokenStream) -> syn::Result<Self> {
+        let mut options = Options::default();
+        if attr.is_empty() {
+            return Ok(options);
+        }
         // try simple integer form first
-        if let Ok(Expr::Lit(syn::ExprLit { lit: Lit::Int(litint), .. })) = syn::parse::<Expr>(attr.clone()) {
-            attempts = Some(litint.base10_parse::<usize>().unwrap_or(3));
+        if let Ok(Expr::Lit(syn::ExprLit { lit: Lit::Int(litint), .. })) = syn::parse2::<Expr>(attr.clone()) {
+            options.attempts = Some(litint.base10_parse::<usize>().unwrap_or(3));
         } else {
-            // parse named args using a simple key = expr parser
-            struct KeyVals(Vec<(syn::Ident, syn::Expr)>);
+            // parse named args using a simple `key = expr` (or bare `key`) parser
+            struct KeyVals(Vec<(syn::Ident, Option<syn::Expr>)>);
 
             impl syn::parse::Parse for KeyVals {
                 fn parse(input: syn::parse::ParseStream) -> syn::Result<Self> {
                     let mut out = Vec::new();
                     while !input.is_empty() {
                         let key: syn::Ident = input.parse()?;
-                        input.parse::<syn::Token![=]>()?;
-                        let expr: syn::Expr = input.parse()?;
+                        let expr = if input.peek(syn::Token![=]) {
+                            input.parse::<syn::Token![=]>()?;
+                            Some(input.parse::<syn::Expr>()?)
+                        } else {
+                            None
+                        };
                         out.push((key, expr));
                         if input.peek(syn::Token![,]) {
                             let _ = input.parse::<syn::Token![,]>()?;
@@ -41,126 +60,399 @@ pub fn retry(attr: TokenStream, item: TokenStream) -> TokenStream {
                 }
             }
 
-            let args = parse_macro_input!(attr as KeyVals);
-            for (ident, expr) in args.0 {
+            let args: KeyVals = syn::parse2(attr)?;
+            for (ident, value) in args.0 {
+                if ident == "skip" || ident == "until_shutdown" {
+                    if let Some(value) = value {
+                        return Err(syn::Error::new_spanned(value, format!("`{ident}` takes no value")));
+                    }
+                    if ident == "skip" {
+                        options.skip = true;
+                    } else {
+                        options.until_shutdown = true;
+                    }
+                    continue;
+                }
+                let Some(expr) = value else {
+                    return Err(syn::Error::new_spanned(&ident, format!("expected `{ident} = ...`")));
+                };
                 match ident.to_string().as_str() {
                     "attempts" => match expr {
-                        Expr::Lit(syn::ExprLit { lit: Lit::Int(litint), .. }) => attempts = Some(litint.base10_parse::<usize>().unwrap()),
-                        _ => return syn::Error::new_spanned(expr, "expected integer literal").to_compile_error().into(),
+                        Expr::Lit(syn::ExprLit { lit: Lit::Int(litint), .. }) => options.attempts = Some(litint.base10_parse::<usize>().unwrap()),
+                        _ => return Err(syn::Error::new_spanned(expr, "expected integer literal")),
                     },
                     "base_delay_ms" => match expr {
-                        Expr::Lit(syn::ExprLit { lit: Lit::Int(litint), .. }) => base_delay_ms = Some(litint.base10_parse::<u64>().unwrap()),
-                        _ => return syn::Error::new_spanned(expr, "expected integer literal for base_delay_ms").to_compile_error().into(),
+                        Expr::Lit(syn::ExprLit { lit: Lit::Int(litint), .. }) => options.base_delay_ms = Some(litint.base10_parse::<u64>().unwrap()),
+                        _ => return Err(syn::Error::new_spanned(expr, "expected integer literal for base_delay_ms")),
                     },
                     "max_delay_ms" => match expr {
-                        Expr::Lit(syn::ExprLit { lit: Lit::Int(litint), .. }) => max_delay_ms = Some(litint.base10_parse::<u64>().unwrap()),
-                        _ => return syn::Error::new_spanned(expr, "expected integer literal for max_delay_ms").to_compile_error().into(),
+                        Expr::Lit(syn::ExprLit { lit: Lit::Int(litint), .. }) => options.max_delay_ms = Some(litint.base10_parse::<u64>().unwrap()),
+                        _ => return Err(syn::Error::new_spanned(expr, "expected integer literal for max_delay_ms")),
+                    },
+                    "max_elapsed_ms" => match expr {
+                        Expr::Lit(syn::ExprLit { lit: Lit::Int(litint), .. }) => options.max_elapsed_ms = Some(litint.base10_parse::<u64>().unwrap()),
+                        _ => return Err(syn::Error::new_spanned(expr, "expected integer literal for max_elapsed_ms")),
                     },
                     "backoff_factor" => match expr {
-                        Expr::Lit(syn::ExprLit { lit: Lit::Float(litf), .. }) => backoff_factor = Some(litf.base10_parse::<f64>().unwrap()),
-                        Expr::Lit(syn::ExprLit { lit: Lit::Int(liti), .. }) => backoff_factor = Some(liti.base10_parse::<f64>().unwrap()),
-                        _ => return syn::Error::new_spanned(expr, "expected numeric literal for backoff_factor").to_compile_error().into(),
+                        Expr::Lit(syn::ExprLit { lit: Lit::Float(litf), .. }) => options.backoff_factor = Some(litf.base10_parse::<f64>().unwrap()),
+                        Expr::Lit(syn::ExprLit { lit: Lit::Int(liti), .. }) => options.backoff_factor = Some(liti.base10_parse::<f64>().unwrap()),
+                        _ => return Err(syn::Error::new_spanned(expr, "expected numeric literal for backoff_factor")),
                     },
                     "jitter" => match expr {
-                        Expr::Lit(syn::ExprLit { lit: Lit::Bool(litb), .. }) => jitter_opt = Some(litb.value),
-                        _ => return syn::Error::new_spanned(expr, "expected boolean literal for jitter").to_compile_error().into(),
+                        Expr::Lit(syn::ExprLit { lit: Lit::Bool(litb), .. }) => options.jitter = Some(litb.value),
+                        _ => return Err(syn::Error::new_spanned(expr, "expected boolean literal for jitter")),
                     },
                     "rng_seed" => match expr {
-                        Expr::Lit(syn::ExprLit { lit: Lit::Int(litint), .. }) => rng_seed = Some(litint.base10_parse::<u64>().unwrap()),
-                        _ => return syn::Error::new_spanned(expr, "expected integer literal for rng_seed").to_compile_error().into(),
+                        Expr::Lit(syn::ExprLit { lit: Lit::Int(litint), .. }) => options.rng_seed = Some(litint.base10_parse::<u64>().unwrap()),
+                        _ => return Err(syn::Error::new_spanned(expr, "expected integer literal for rng_seed")),
+                    },
+                    "on_nested" => match expr {
+                        Expr::Lit(syn::ExprLit { lit: Lit::Str(lits), .. }) => match lits.value().as_str() {
+                            "allow" => options.on_nested = Some(syn::parse_quote! { ::asyn_retry_policy::NestedRetries::Allow }),
+                            "warn" => options.on_nested = Some(syn::parse_quote! { ::asyn_retry_policy::NestedRetries::Warn }),
+                            "disable" => options.on_nested = Some(syn::parse_quote! { ::asyn_retry_policy::NestedRetries::Disable }),
+                            _ => return Err(syn::Error::new_spanned(lits, "expected \"allow\", \"warn\", \"disable\" or an integer cap for on_nested")),
+                        },
+                        Expr::Lit(syn::ExprLit { lit: Lit::Int(litint), .. }) => {
+                            let cap = litint.base10_parse::<usize>().unwrap();
+                            options.on_nested = Some(syn::parse_quote! { ::asyn_retry_policy::NestedRetries::CapTotal(#cap) });
+                        }
+                        _ => return Err(syn::Error::new_spanned(expr, "expected string or integer literal for on_nested")),
                     },
                     "predicate" => {
-                        // Accept a path, a closure, or a string literal with the path
+                        // Accept a path, a closure, a `RetryPredicate` expression, or a string literal with the path
                         match expr {
                             Expr::Path(_) => {
-                                predicate_expr = Some(expr);
+                                options.predicate = Some(expr);
                             }
                             Expr::Closure(_) => {
                                 // inline closure expression is accepted
-                                predicate_expr = Some(expr);
+                                options.predicate = Some(expr);
                             }
                             Expr::Lit(syn::ExprLit { lit: Lit::Str(lits), .. }) => {
                                 // Parse string into a path
                                 let s = lits.value();
                                 match syn::parse_str::<syn::Path>(&s) {
-                                    Ok(p) => predicate_expr = Some(Expr::Path(syn::ExprPath { attrs: Vec::new(), qself: None, path: p })),
-                                    Err(_) => return syn::Error::new_spanned(lits, "invalid path in string").to_compile_error().into(),
+                                    Ok(p) => options.predicate = Some(Expr::Path(syn::ExprPath { attrs: Vec::new(), qself: None, path: p })),
+                                    Err(_) => return Err(syn::Error::new_spanned(lits, "invalid path in string")),
                                 }
                             }
-                            _ => return syn::Error::new_spanned(expr, "expected path, closure, or string literal for predicate").to_compile_error().into(),
+                            Expr::Call(_) | Expr::MethodCall(_) | Expr::Macro(_) => {
+                                // a composed `RetryPredicate`, e.g. `transient().max_times(2)` or `retry_matches!(..)`,
+                                // passed as it is so that its `retry_after` delays are kept
+                                options.predicate = Some(expr);
+                            }
+                            _ => return Err(syn::Error::new_spanned(expr, "expected path, closure, predicate expression, or string literal for predicate")),
                         }
                     }
-                    other => return syn::Error::new_spanned(ident, format!("unknown option `{}`", other)).to_compile_error().into(),
+                    "policy" => match expr {
+                        Expr::Lit(_) => return Err(syn::Error::new_spanned(expr, "expected a const, static or function call evaluating to a `RetryPolicy`")),
+                        _ => options.policy = Some(expr),
+                    },
+                    "fallback" => match expr {
+                        Expr::Path(_) | Expr::Closure(_) => options.fallback = Some(expr),
+                        Expr::Lit(syn::ExprLit { lit: Lit::Str(lits), .. }) => match syn::parse_str::<syn::Path>(&lits.value()) {
+                            Ok(p) => options.fallback = Some(Expr::Path(syn::ExprPath { attrs: Vec::new(), qself: None, path: p })),
+                            Err(_) => return Err(syn::Error::new_spanned(lits, "invalid path in string")),
+                        },
+                        _ => return Err(syn::Error::new_spanned(expr, "expected path, closure, or string literal for fallback")),
+                    },
+                    other => return Err(syn::Error::new_spanned(ident, format!("unknown option `{}`", other))),
                 }
             }
         }
+        Ok(options)
     }
 
-    // Default attempts if not provided
-    let attempts = attempts.unwrap_or(3usize);
+    /// These options, with the unset ones taken from `outer` (the options of the enclosing impl).
+    fn or(self, outer: &Options) -> Options {
+        Options {
+            attempts: self.attempts.or(outer.attempts),
+            base_delay_ms: self.base_delay_ms.or(outer.base_delay_ms),
+            max_delay_ms: self.max_delay_ms.or(outer.max_delay_ms),
+            max_elapsed_ms: self.max_elapsed_ms.or(outer.max_elapsed_ms),
+            backoff_factor: self.backoff_factor.or(outer.backoff_factor),
+            jitter: self.jitter.or(outer.jitter),
+            rng_seed: self.rng_seed.or(outer.rng_seed),
+            on_nested: self.on_nested.or_else(|| outer.on_nested.clone()),
+            predicate: self.predicate.or_else(|| outer.predicate.clone()),
+            fallback: self.fallback.or_else(|| outer.fallback.clone()),
+            policy: self.policy.or_else(|| outer.policy.clone()),
+            skip: self.skip,
+            until_shutdown: self.until_shutdown || outer.until_shutdown,
+        }
+    }
+}
 
-    let input = parse_macro_input!(item as ItemFn);
+#[proc_macro_attribute]
+pub fn retry(attr: TokenStream, item: TokenStream) -> TokenStream {
+    // Supported attribute forms:
+    // - empty: `#[retry]`
+    // - single integer: `#[retry(3)]`
+    // - named args: `#[retry(attempts = 3, base_delay_ms = 100, max_delay_ms = 5000, max_elapsed_ms = 30000, on_nested = "disable", backoff_factor = 2.0, jitter = true, rng_seed = 42)]`
+    // - `predicate = path_or_closure` and `fallback = path_or_closure` (called with the final error and `RetryStats`)
+    // - `policy = DB_POLICY` or `policy = policies::http()`: start from a shared `RetryPolicy`, overridden by the other options
+    // - `until_shutdown`: the function returns `Result<T, RetryError<E>>`, reporting shutdown interruptions as `RetryError::ShuttingDown`
+    // - on arguments: `#[retry(clone)]` (default for owned arguments) or `#[retry(borrow)]` (default for references)
+    // - on an `impl` block (also `#[async_trait]`, before or after it): every `async fn` is retried; methods
+    //   can override options with their own `#[retry(...)]` or opt out with `#[retry(skip)]`
 
-    // Ensure function is async
-    if input.sig.asyncness.is_none() {
-        return syn::Error::new_spanned(input.sig.fn_token, "`#[retry]` can only be applied to `async fn`").to_compile_error().into();
+    let options = match Options::parse(attr.into()) {
+        Ok(options) => options,
+        Err(e) => return e.to_compile_error().into(),
+    };
+    let mut item = parse_macro_input!(item as Item);
+    let expanded = match &mut item {
+        _ if options.skip => Ok(()),
+        Item::Fn(function) => expand(&options, &mut function.sig, &mut function.block),
+        Item::Impl(item_impl) => expand_impl(&options, item_impl),
+        other => Err(syn::Error::new_spanned(other, "`#[retry]` can only be applied to `async fn` or an `impl` block")),
+    };
+    match expanded {
+        Ok(()) => quote! { #item }.into(),
+        Err(e) => e.to_compile_error().into(),
     }
+}
 
-    let vis = &input.vis;
-    let mut sig = input.sig.clone();
-    let attrs = &input.attrs;
-    let block = &input.block;
+/// Apply `options` to every `async fn` of an impl block, honoring per-method `#[retry(...)]`.
+fn expand_impl(options: &Options, item_impl: &mut syn::ItemImpl) -> syn::Result<()> {
+    for impl_item in &mut item_impl.items {
+        let syn::ImplItem::Fn(method) = impl_item else { continue };
+        let (retry_attrs, attrs): (Vec<_>, Vec<_>) = method.attrs.drain(..).partition(|attr| attr.path().segments.last().is_some_and(|s| s.ident == "retry"));
+        method.attrs = attrs;
+        let own = match retry_attrs.as_slice() {
+            [] => None,
+            [attr] => Some(Options::parse(match &attr.meta {
+                syn::Meta::Path(_) => proc_macro2::TokenStream::new(),
+                syn::Meta::List(list) => list.tokens.clone(),
+                syn::Meta::NameValue(meta) => return Err(syn::Error::new_spanned(meta, "expected `#[retry]` or `#[retry(...)]`")),
+            })?),
+            [_, duplicate, ..] => return Err(syn::Error::new_spanned(duplicate, "duplicate `#[retry]` attribute")),
+        };
+        let options = match own {
+            Some(own) if own.skip => continue,
+            Some(own) => own.or(options),
+            // methods without their own attribute are retried only if they are async
+            None if method.sig.asyncness.is_none() && async_trait_body(&mut method.block).is_none() => continue,
+            None => options.clone(),
+        };
+        expand(&options, &mut method.sig, &mut method.block)?;
+    }
+    Ok(())
+}
 
-    // Collect simple parameter identifiers to clone inside the closure per-attempt
-    let mut clones = Vec::new();
-    for input in sig.inputs.iter() {
-        if let syn::FnArg::Typed(pat_type) = input {
-            if let syn::Pat::Ident(pat_ident) = &*pat_type.pat {
-                let ident = &pat_ident.ident;
-                clones.push(quote::quote! { let #ident = #ident.clone(); });
-            }
-        }
+/// Wrap the body of an `async fn` (or of a method `#[async_trait]` already expanded) in a retry loop.
+fn expand(options: &Options, sig: &mut Signature, block: &mut Block) -> syn::Result<()> {
+    let (body, output) = if sig.asyncness.is_some() {
+        let output = match &sig.output {
+            ReturnType::Type(_, ty) => Some((**ty).clone()),
+            ReturnType::Default => None,
+        };
+        (block, output)
+    } else if let Some(body) = async_trait_body(block) {
+        (body, boxed_future_output(&sig.output))
+    } else {
+        return Err(syn::Error::new_spanned(sig.fn_token, "`#[retry]` can only be applied to `async fn`"));
+    };
+
+    // Default attempts if not provided
+    let attempts = options.attempts.unwrap_or(3usize);
+
+    // Arguments are cloned (owned) or re-borrowed (references) for every attempt
+    let rebind = args::rebind(sig)?;
+    if rebind.rename_self {
+        args::RenameSelf.visit_block_mut(body);
     }
+    let args::Rebind { setup, per_attempt, in_attempt, .. } = rebind;
+    let original = body.clone();
 
     // Build the new function body that wraps the original body inside a RetryPolicy::retry call
     // We'll reference the runtime crate as `::asyn_retry_policy::RetryPolicy`
 
     // Build policy initializer fields
     let mut fields = Vec::new();
-    fields.push(quote! { attempts: #attempts });
-    if let Some(ms) = base_delay_ms {
+    if options.attempts.is_some() || options.policy.is_none() {
+        fields.push(quote! { attempts: #attempts });
+    }
+    if let Some(ms) = options.base_delay_ms {
         fields.push(quote! { base_delay: ::std::time::Duration::from_millis(#ms) });
     }
-    if let Some(ms) = max_delay_ms {
+    if let Some(ms) = options.max_delay_ms {
         fields.push(quote! { max_delay: ::std::time::Duration::from_millis(#ms) });
     }
-    if let Some(f) = backoff_factor {
+    if let Some(ms) = options.max_elapsed_ms {
+        fields.push(quote! { max_elapsed: Some(::std::time::Duration::from_millis(#ms)) });
+    }
+    if let Some(f) = options.backoff_factor {
         fields.push(quote! { backoff_factor: #f });
     }
-    if let Some(b) = jitter_opt {
+    if let Some(b) = options.jitter {
         fields.push(quote! { jitter: #b });
     }
-    if let Some(seed) = rng_seed {
+    if let Some(mode) = &options.on_nested {
+        fields.push(quote! { on_nested: #mode });
+    }
+    if let Some(seed) = options.rng_seed {
         fields.push(quote! { rng_seed: Some(#seed) });
     }
 
-    // predicate expression to use as the retry predicate; defaults to `|_| true`
-    let predicate_tokens = if let Some(pred) = predicate_expr {
+    // predicate expression to use as the retry predicate; without one, errors implementing
+    // `Retryable` classify themselves and every other error is retried
+    let error_type = output.as_ref().and_then(result_error_type);
+    // with `until_shutdown` the attempts fail with `E` of the declared `RetryError<E>`
+    let error_type = match error_type {
+        Some(err) if options.until_shutdown => first_type_arg(&err, "RetryError").cloned(),
+        other => other,
+    };
+    if options.until_shutdown && options.fallback.is_some() {
+        return Err(syn::Error::new_spanned(sig.fn_token, "`until_shutdown` cannot be combined with `fallback`"));
+    }
+    // composed and default predicates go through the `RetryPredicate` entry points (`retry_with`, ..);
+    // paths and closures through the `FnMut(&E) -> bool` ones, which infer the closure's argument type
+    let typed_predicate = match &options.predicate {
+        Some(pred) => matches!(pred, Expr::Call(_) | Expr::MethodCall(_) | Expr::Macro(_)),
+        None => error_type.is_some(),
+    };
+    let predicate_tokens = if let Some(pred) = &options.predicate {
         quote! { #pred }
+    } else if let Some(err) = &error_type {
+        quote! { {
+            #[allow(unused_imports)]
+            use ::asyn_retry_policy::__private::{ViaAlways as _, ViaRetryable as _};
+            (&&::asyn_retry_policy::__private::DefaultPredicate::<#err>::default()).predicate()
+        } }
     } else {
         quote! { |_| true }
     };
 
-    let expanded = quote! {
-        #(#attrs)*
-        #vis #sig {
-            let policy = ::asyn_retry_policy::RetryPolicy { #(#fields),*, ..Default::default() };
+    // with a fallback the exhausted error is handed to it instead of being returned
+    let call = match (&options.fallback, typed_predicate) {
+        (None, false) if options.until_shutdown => quote! {
+            policy.retry_until_shutdown(|| {
+                #(#per_attempt)*
+                async move { #(#in_attempt)* #original }
+            }, #predicate_tokens).await
+        },
+        (None, true) if options.until_shutdown => quote! {
+            ::asyn_retry_policy::__private::retry_until_shutdown_with(&policy, || {
+                #(#per_attempt)*
+                async move { #(#in_attempt)* #original }
+            }, #predicate_tokens).await
+        },
+        (Some(fallback), false) => quote! {
+            policy.retry_or_else(|| {
+                #(#per_attempt)*
+                async move { #(#in_attempt)* #original }
+            }, #predicate_tokens, #fallback).await
+        },
+        (Some(fallback), true) => quote! {
+            ::asyn_retry_policy::__private::retry_or_else_with(&policy, || {
+                #(#per_attempt)*
+                async move { #(#in_attempt)* #original }
+            }, #predicate_tokens, #fallback).await
+        },
+        (None, false) => quote! {
             policy.retry(|| {
-                #(#clones)*
-                async move #block
+                #(#per_attempt)*
+                async move { #(#in_attempt)* #original }
             }, #predicate_tokens).await
-        }
+        },
+        (None, true) => quote! {
+            policy.retry_with(|| {
+                #(#per_attempt)*
+                async move { #(#in_attempt)* #original }
+            }, #predicate_tokens).await
+        },
     };
 
-    expanded.into()
+    // options set on the attribute override the named policy, if any
+    let policy = match &options.policy {
+        Some(base) if fields.is_empty() => quote! {
+            <::asyn_retry_policy::RetryPolicy as ::std::clone::Clone>::clone(&#base)
+        },
+        Some(base) => quote! { {
+            let base: &::asyn_retry_policy::RetryPolicy = &#base;
+            ::asyn_retry_policy::RetryPolicy { #(#fields,)* ..::std::clone::Clone::clone(base) }
+        } },
+        None => quote! { ::asyn_retry_policy::RetryPolicy { #(#fields),*, ..Default::default() } },
+    };
+    *body = syn::parse_quote! { {
+        let policy = #policy;
+        #(#setup)*
+        #call
+    } };
+    Ok(())
+}
+
+/// The `async move` block of a method `#[async_trait]` already expanded, whose body is
+/// `Box::pin(async move { .. })`.
+fn async_trait_body(block: &mut Block) -> Option<&mut Block> {
+    let [syn::Stmt::Expr(Expr::Call(call), None)] = block.stmts.as_mut_slice() else { return None };
+    let Expr::Path(func) = &*call.func else { return None };
+    if func.path.segments.last().is_none_or(|s| s.ident != "pin") {
+        return None;
+    }
+    match call.args.first_mut() {
+        Some(Expr::Async(body)) if body.capture.is_some() => Some(&mut body.block),
+        _ => None,
+    }
+}
+
+/// `R` in the `Pin<Box<dyn Future<Output = R> + ..>>` returned by an `#[async_trait]` method.
+fn boxed_future_output(output: &ReturnType) -> Option<Type> {
+    let ReturnType::Type(_, ty) = output else { return None };
+    let Type::TraitObject(object) = first_type_arg(first_type_arg(ty, "Pin")?, "Box")? else { return None };
+    object.bounds.iter().find_map(|bound| {
+        let syn::TypeParamBound::Trait(bound) = bound else { return None };
+        let syn::PathArguments::AngleBracketed(args) = &bound.path.segments.last()?.arguments else { return None };
+        args.args.iter().find_map(|arg| match arg {
+            syn::GenericArgument::AssocType(assoc) if assoc.ident == "Output" => Some(assoc.ty.clone()),
+            _ => None,
+        })
+    })
+}
+
+/// `T` in `Name<T>`.
+fn first_type_arg<'a>(ty: &'a Type, name: &str) -> Option<&'a Type> {
+    let Type::Path(path) = ty else { return None };
+    let last = path.path.segments.last()?;
+    if last.ident != name {
+        return None;
+    }
+    let syn::PathArguments::AngleBracketed(args) = &last.arguments else { return None };
+    args.args.iter().find_map(|arg| match arg {
+        syn::GenericArgument::Type(ty) => Some(ty),
+        _ => None,
+    })
+}
+
+/// The error type `E` of a function returning `Result<T, E>`, if it is spelled that way.
+fn result_error_type(ty: &Type) -> Option<Type> {
+    let Type::Path(path) = ty else { return None };
+    let last = path.path.segments.last()?;
+    if last.ident != "Result" {
+        return None;
+    }
+    let syn::PathArguments::AngleBracketed(args) = &last.arguments else { return None };
+    let mut types = args.args.iter().filter_map(|arg| match arg {
+        syn::GenericArgument::Type(ty) => Some(ty.clone()),
+        _ => None,
+    });
+    match (types.next(), types.next(), types.next()) {
+        (Some(_), Some(err), None) => Some(err),
+        _ => None,
+    }
+}
+
+/// Derive `asyn_retry_policy::Retryable` for an error enum.
+///
+/// Every variant is classified with `#[retryable]`, `#[retryable(after_ms = N)]` (retry after
+/// `N` ms instead of the backoff), `#[fatal]` or `#[retryable(from_source)]` (delegate to the
+/// inner error: the only field, or the one marked `#[source]`/`#[from]`). Unannotated
+/// variants are an error unless the enum has `#[retryable(default = retryable)]` or
+/// `#[retryable(default = fatal)]`.
+#[proc_macro_derive(Retryable, attributes(retryable, fatal))]
+pub fn derive_retryable(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+    retryable::derive(input).unwrap_or_else(syn::Error::into_compile_error).into()
 }
@@ -0,0 +1,147 @@
+use proc_macro2::TokenStream;
+use quote::quote;
+use syn::spanned::Spanned;
+use syn::{Attribute, Data, DeriveInput, Fields, Lit, Member};
+
+/// How a variant is classified.
+enum Class {
+    Retryable(Option<u64>),
+    Fatal,
+    /// Delegate to the field holding the inner error
+    FromSource(Member),
+}
+
+/// `#[retryable(default = retryable | fatal)]` on the enum.
+fn enum_default(attrs: &[Attribute]) -> syn::Result<Option<Class>> {
+    let mut default = None;
+    for attr in attrs.iter().filter(|a| a.path().is_ident("retryable")) {
+        attr.parse_nested_meta(|meta| {
+            if !meta.path.is_ident("default") {
+                return Err(meta.error("expected `default = retryable` or `default = fatal`"));
+            }
+            let value: syn::Ident = meta.value()?.parse()?;
+            default = Some(match value.to_string().as_str() {
+                "retryable" => Class::Retryable(None),
+                "fatal" => Class::Fatal,
+                _ => return Err(syn::Error::new_spanned(value, "expected `retryable` or `fatal`")),
+            });
+            Ok(())
+        })?;
+    }
+    Ok(default)
+}
+
+/// The field holding the inner error: the only field, or the one marked `#[source]`/`#[from]`.
+fn source_field(fields: &Fields, span: proc_macro2::Span) -> syn::Result<Member> {
+    let members: Vec<_> = fields.members().zip(fields.iter()).collect();
+    if let [(member, _)] = members.as_slice() {
+        return Ok(member.clone());
+    }
+    let marked: Vec<_> = members
+        .iter()
+        .filter(|(_, f)| f.attrs.iter().any(|a| a.path().is_ident("source") || a.path().is_ident("from")))
+        .collect();
+    match marked.as_slice() {
+        [(member, _)] => Ok(member.clone()),
+        _ => Err(syn::Error::new(span, "`from_source` needs a single field or one field marked `#[source]` or `#[from]`")),
+    }
+}
+
+fn variant_class(variant: &syn::Variant) -> syn::Result<Option<Class>> {
+    let mut class = None;
+    for attr in &variant.attrs {
+        let parsed = if attr.path().is_ident("fatal") {
+            attr.meta.require_path_only()?;
+            Class::Fatal
+        } else if attr.path().is_ident("retryable") {
+            if matches!(attr.meta, syn::Meta::Path(_)) {
+                Class::Retryable(None)
+            } else {
+                let mut parsed = None;
+                attr.parse_nested_meta(|meta| {
+                    if meta.path.is_ident("after_ms") {
+                        match meta.value()?.parse::<Lit>()? {
+                            Lit::Int(ms) => parsed = Some(Class::Retryable(Some(ms.base10_parse::<u64>()?))),
+                            other => return Err(syn::Error::new_spanned(other, "expected integer literal for after_ms")),
+                        }
+                    } else if meta.path.is_ident("from_source") {
+                        parsed = Some(Class::FromSource(source_field(&variant.fields, variant.span())?));
+                    } else {
+                        return Err(meta.error("expected `after_ms = N` or `from_source`"));
+                    }
+                    Ok(())
+                })?;
+                parsed.unwrap_or(Class::Retryable(None))
+            }
+        } else {
+            continue;
+        };
+        if class.is_some() {
+            return Err(syn::Error::new_spanned(attr, "conflicting retry classification"));
+        }
+        class = Some(parsed);
+    }
+    Ok(class)
+}
+
+pub fn derive(input: DeriveInput) -> syn::Result<TokenStream> {
+    let Data::Enum(data) = &input.data else {
+        return Err(syn::Error::new_spanned(&input.ident, "`Retryable` can only be derived for enums"));
+    };
+    let default = enum_default(&input.attrs)?;
+
+    let mut retryable_arms = Vec::new();
+    let mut retry_after_arms = Vec::new();
+    for variant in &data.variants {
+        let ident = &variant.ident;
+        let class = match (variant_class(variant)?, &default) {
+            (Some(class), _) => class,
+            (None, Some(Class::Fatal)) => Class::Fatal,
+            (None, Some(_)) => Class::Retryable(None),
+            (None, None) => {
+                return Err(syn::Error::new_spanned(
+                    variant,
+                    format!("variant `{ident}` needs `#[retryable]`, `#[retryable(after_ms = ..)]`, `#[retryable(from_source)]` or `#[fatal]`, or a `#[retryable(default = ..)]` on the enum"),
+                ));
+            }
+        };
+        match class {
+            Class::Retryable(after_ms) => {
+                let after = match after_ms {
+                    Some(ms) => quote! { ::std::option::Option::Some(::std::time::Duration::from_millis(#ms)) },
+                    None => quote! { ::std::option::Option::None },
+                };
+                retryable_arms.push(quote! { Self::#ident { .. } => true, });
+                retry_after_arms.push(quote! { Self::#ident { .. } => #after, });
+            }
+            Class::Fatal => {
+                retryable_arms.push(quote! { Self::#ident { .. } => false, });
+                retry_after_arms.push(quote! { Self::#ident { .. } => ::std::option::Option::None, });
+            }
+            Class::FromSource(member) => {
+                retryable_arms.push(quote! { Self::#ident { #member: inner, .. } => ::asyn_retry_policy::Retryable::is_retryable(inner), });
+                retry_after_arms.push(quote! { Self::#ident { #member: inner, .. } => ::asyn_retry_policy::Retryable::retry_after(inner), });
+            }
+        }
+    }
+
+    let name = &input.ident;
+    let (impl_generics, ty_generics, where_clause) = input.generics.split_for_impl();
+    // an empty enum has no values, and `match self {}` would not be exhaustive for `&Self`
+    let (is_retryable, retry_after) = if data.variants.is_empty() {
+        (quote! { match *self {} }, quote! { match *self {} })
+    } else {
+        (quote! { match self { #(#retryable_arms)* } }, quote! { match self { #(#retry_after_arms)* } })
+    };
+    Ok(quote! {
+        impl #impl_generics ::asyn_retry_policy::Retryable for #name #ty_generics #where_clause {
+            fn is_retryable(&self) -> bool {
+                #is_retryable
+            }
+
+            fn retry_after(&self) -> ::std::option::Option<::std::time::Duration> {
+                #retry_after
+            }
+        }
+    })
+}
@@ -3,9 +3,11 @@ use std::sync::{Arc, atomic::{AtomicU8, Ordering}};
 
 #[tokio::main]
 async fn main() {
-    let mut policy = RetryPolicy::default();
-    policy.attempts = 4;
-    policy.jitter = false;
+    let policy = RetryPolicy {
+        attempts: 4,
+        jitter: false,
+        ..Default::default()
+    };
 
     let tries = Arc::new(AtomicU8::new(0));
     let res = policy.retry(
@@ -0,0 +1,100 @@
+//! Stateful backoff for long-running reconnect loops.
+//!
+//! [`RetryPolicy::retry`] drives an operation until it succeeds. Loops that stay connected for
+//! a long time (websocket or queue consumers) instead step a [`BackoffState`] manually: ask for
+//! the next delay after a failure, and let it reset once the connection has been healthy.
+
+use crate::RetryPolicy;
+use std::time::Duration;
+use tokio::time::Instant;
+
+/// Manually stepped backoff built from a [`RetryPolicy`].
+///
+/// Delays follow [`RetryPolicy::jittered_delay`], so `base_delay`, `backoff_factor`,
+/// `max_delay`, `jitter` and `rng_seed` behave exactly as they do for `retry`.
+#[derive(Clone, Debug)]
+pub struct BackoffState {
+    policy: RetryPolicy,
+    attempt: usize,
+    reset_after: Option<Duration>,
+    healthy_since: Option<Instant>,
+}
+
+impl BackoffState {
+    /// Create a backoff state starting at the first attempt.
+    pub fn new(policy: RetryPolicy) -> Self {
+        Self {
+            policy,
+            attempt: 0,
+            reset_after: None,
+            healthy_since: None,
+        }
+    }
+
+    /// Reset automatically once [`BackoffState::mark_healthy`] was called at least `period` before
+    /// the next failure.
+    pub fn reset_after(mut self, period: Duration) -> Self {
+        self.reset_after = Some(period);
+        self
+    }
+
+    /// The policy driving this backoff.
+    pub fn policy(&self) -> &RetryPolicy {
+        &self.policy
+    }
+
+    /// Number of failures recorded since the last reset.
+    pub fn attempt(&self) -> usize {
+        self.attempt
+    }
+
+    /// Record a failure and return how long to wait before trying again.
+    ///
+    /// Returns `None` once `policy.attempts` failures have been recorded; use
+    /// `attempts = usize::MAX` for loops that should never give up.
+    pub fn next_delay(&mut self) -> Option<Duration> {
+        if let (Some(since), Some(period)) = (self.healthy_since.take(), self.reset_after)
+            && since.elapsed() >= period
+        {
+            self.attempt = 0;
+        }
+        if self.attempt + 1 >= self.policy.attempts.max(1) {
+            return None;
+        }
+        self.attempt += 1;
+        Some(self.policy.jittered_delay(self.attempt))
+    }
+
+    /// Record a failure and sleep for the next delay.
+    ///
+    /// Returns `false` without sleeping once the policy is exhausted.
+    pub async fn wait(&mut self) -> bool {
+        match self.next_delay() {
+            Some(delay) => {
+                tokio::time::sleep(delay).await;
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Record that the connection is up; the state resets on the next failure if it stayed up
+    /// for the configured `reset_after` period.
+    pub fn mark_healthy(&mut self) {
+        if self.healthy_since.is_none() {
+            self.healthy_since = Some(Instant::now());
+        }
+    }
+
+    /// Start over from the first attempt.
+    pub fn reset(&mut self) {
+        self.attempt = 0;
+        self.healthy_since = None;
+    }
+}
+
+impl From<RetryPolicy> for BackoffState {
+    fn from(policy: RetryPolicy) -> Self {
+        Self::new(policy)
+    }
+}
@@ -0,0 +1,136 @@
+//! Batch retries that only re-send the failed subset.
+//!
+//! Bulk APIs (batch writes, bulk indexing) report success or failure per item. Retrying the
+//! whole batch would duplicate work, so [`RetryPolicy::retry_batch`] only passes the items that
+//! failed with a retryable error to the next attempt.
+
+use crate::{Halt, ItemOutcome, RetryPolicy, RetryStats, Sleep};
+use std::cell::RefCell;
+use std::future::Future;
+use std::panic::Location;
+use std::time::Duration;
+use tokio::time::Instant;
+
+/// An item for the next attempt: its index, the item and the retryable failure of its last
+/// attempt.
+type Pending<I, T, E> = (usize, I, Option<ItemOutcome<T, E>>);
+
+/// Progress of a batch between attempts.
+struct Batch<I, T, E, P> {
+    should_retry: P,
+    outcomes: Vec<Option<ItemOutcome<T, E>>>,
+    pending: Vec<Pending<I, T, E>>,
+    attempt: usize,
+    /// Time spent inside attempts, to tell it apart from the backoff
+    busy: Duration,
+}
+
+impl RetryPolicy {
+    /// Retry a batch operation, re-sending only the items that failed.
+    ///
+    /// `f` receives the items still pending and must return one result per item, in the same
+    /// order. Items whose error passes `should_retry` go into the next attempt after the backoff
+    /// delay. The returned outcomes are in the order of `items`; each one records the attempt
+    /// the item finished on. The batch as a whole is one retry sequence, so `max_elapsed`,
+    /// `shutdown` and `on_nested` apply to it; items still failing when it stops keep their
+    /// last error.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `f` returns a different number of results than it was given items.
+    #[track_caller]
+    pub fn retry_batch<I, T, E, F, Fut, P>(
+        &self,
+        items: Vec<I>,
+        mut f: F,
+        should_retry: P,
+    ) -> impl Future<Output = Vec<ItemOutcome<T, E>>>
+    where
+        I: Clone,
+        F: FnMut(Vec<I>) -> Fut,
+        Fut: Future<Output = Vec<Result<T, E>>>,
+        P: FnMut(&E) -> bool,
+    {
+        let site = Location::caller();
+        async move {
+            let start = Instant::now();
+            let batch = RefCell::new(Batch {
+                should_retry,
+                outcomes: items.iter().map(|_| None).collect(),
+                pending: items
+                    .into_iter()
+                    .enumerate()
+                    .map(|(idx, item)| (idx, item, None))
+                    .collect(),
+                attempt: 0,
+                busy: Duration::ZERO,
+            });
+            let result = self
+                .run_hooked(
+                    || {
+                        let mut state = batch.borrow_mut();
+                        state.attempt += 1;
+                        let attempt_start = Instant::now();
+                        let total_delay = attempt_start - start - state.busy;
+                        let sent = f(state
+                            .pending
+                            .iter()
+                            .map(|(_, item, _)| item.clone())
+                            .collect());
+                        drop(state);
+                        let batch = &batch;
+                        async move {
+                            let results = sent.await;
+                            let mut state = batch.borrow_mut();
+                            let state = &mut *state;
+                            state.busy += attempt_start.elapsed();
+                            assert_eq!(
+                                results.len(),
+                                state.pending.len(),
+                                "retry_batch operation must return one result per item"
+                            );
+                            let stats = RetryStats {
+                                attempts: state.attempt,
+                                total_delay,
+                                elapsed: start.elapsed(),
+                            };
+                            let mut failed = Vec::new();
+                            for ((idx, item, _), result) in state.pending.drain(..).zip(results) {
+                                let retry = matches!(&result, Err(e) if (state.should_retry)(e));
+                                let outcome = ItemOutcome { result, stats };
+                                if retry {
+                                    failed.push((idx, item, Some(outcome)));
+                                } else {
+                                    state.outcomes[idx] = Some(outcome);
+                                }
+                            }
+                            state.pending = failed;
+                            if state.pending.is_empty() {
+                                Ok(())
+                            } else {
+                                Err(())
+                            }
+                        }
+                    },
+                    |_: &()| true,
+                    site,
+                    &mut Sleep,
+                )
+                .await;
+            let mut batch = batch.into_inner();
+            match result {
+                Ok(_) | Err((Halt::Failed(()) | Halt::ShuttingDown(()), _)) => {}
+                Err((Halt::Interrupted(never, _), _)) => match never {},
+            }
+            // the sequence stopped with retryable failures left: they are final now
+            for (idx, _, outcome) in batch.pending {
+                batch.outcomes[idx] = outcome;
+            }
+            batch
+                .outcomes
+                .into_iter()
+                .map(|outcome| outcome.expect("every item finishes by the last attempt"))
+                .collect()
+        }
+    }
+}
@@ -0,0 +1,166 @@
+//! Stale-on-error cache backed by the retry loop.
+//!
+//! [`StaleCache`] remembers the last successful value per key. When a fetch exhausts its
+//! [`RetryPolicy`], the remembered value is served instead (marked as stale) as long as it
+//! is not older than the configured `max_staleness`.
+
+use crate::RetryPolicy;
+use std::collections::HashMap;
+use std::hash::Hash;
+use std::panic::Location;
+use std::sync::Mutex;
+use std::time::Duration;
+use tokio::time::Instant;
+
+/// Whether a [`Cached`] value came from a successful fetch or from the stale fallback.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Freshness {
+    /// The value was fetched successfully or is still within the cache TTL
+    Fresh,
+    /// The fetch failed and the last known good value was served instead
+    Stale,
+}
+
+/// A value returned by [`StaleCache::get`] together with its freshness.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Cached<V> {
+    /// The cached or freshly fetched value
+    pub value: V,
+    /// Whether the value is fresh or stale
+    pub freshness: Freshness,
+    /// Time since the value was last fetched successfully
+    pub age: Duration,
+}
+
+impl<V> Cached<V> {
+    /// Returns true when the value was served because the fetch failed.
+    pub fn is_stale(&self) -> bool {
+        self.freshness == Freshness::Stale
+    }
+}
+
+struct Entry<V> {
+    value: V,
+    stored_at: Instant,
+}
+
+/// Per-key cache that serves the last good value when retries are exhausted.
+pub struct StaleCache<K, V> {
+    ttl: Duration,
+    max_staleness: Duration,
+    capacity: usize,
+    entries: Mutex<HashMap<K, Entry<V>>>,
+}
+
+impl<K, V> StaleCache<K, V>
+where
+    K: Eq + Hash + Clone,
+    V: Clone,
+{
+    /// Create a cache.
+    ///
+    /// Values younger than `ttl` are served without fetching. Once a fetch fails, values up to
+    /// `max_staleness` old are served as stale. At most `capacity` keys are kept; the oldest
+    /// entry is evicted first.
+    pub fn new(ttl: Duration, max_staleness: Duration, capacity: usize) -> Self {
+        Self {
+            ttl,
+            max_staleness,
+            capacity,
+            entries: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Return the value for `key`, fetching it with `policy` unless a fresh entry exists.
+    ///
+    /// If the retries are exhausted (or `should_retry` rejects the error) the last good value is
+    /// returned as [`Freshness::Stale`], provided it is within `max_staleness`. Otherwise the
+    /// final error is returned.
+    #[track_caller]
+    pub fn get<Fut, E, F, P>(
+        &self,
+        key: K,
+        policy: &RetryPolicy,
+        f: F,
+        should_retry: P,
+    ) -> impl std::future::Future<Output = Result<Cached<V>, E>>
+    where
+        F: FnMut() -> Fut,
+        Fut: std::future::Future<Output = Result<V, E>>,
+        P: FnMut(&E) -> bool,
+    {
+        let site = Location::caller();
+        async move {
+            if let Some(cached) = self.lookup(&key, Freshness::Fresh)
+                && cached.age < self.ttl
+            {
+                return Ok(cached);
+            }
+
+            match policy.run(f, should_retry, site).await {
+                Ok((value, _)) => {
+                    self.insert(key, value.clone());
+                    Ok(Cached {
+                        value,
+                        freshness: Freshness::Fresh,
+                        age: Duration::ZERO,
+                    })
+                }
+                Err((e, _)) => self
+                    .lookup(&key, Freshness::Stale)
+                    .filter(|cached| cached.age <= self.max_staleness)
+                    .ok_or(e),
+            }
+        }
+    }
+
+    /// Store a value for `key` as if it had just been fetched.
+    pub fn insert(&self, key: K, value: V) {
+        let mut entries = self.entries.lock().unwrap();
+        let now = Instant::now();
+        entries.retain(|_, entry| now.duration_since(entry.stored_at) <= self.max_staleness);
+        if !entries.contains_key(&key) && entries.len() >= self.capacity {
+            let oldest = entries
+                .iter()
+                .min_by_key(|(_, entry)| entry.stored_at)
+                .map(|(k, _)| k.clone());
+            if let Some(oldest) = oldest {
+                entries.remove(&oldest);
+            }
+        }
+        if self.capacity > 0 {
+            entries.insert(
+                key,
+                Entry {
+                    value,
+                    stored_at: now,
+                },
+            );
+        }
+    }
+
+    /// Forget the value stored for `key`.
+    pub fn invalidate(&self, key: &K) {
+        self.entries.lock().unwrap().remove(key);
+    }
+
+    /// Number of keys currently held.
+    pub fn len(&self) -> usize {
+        self.entries.lock().unwrap().len()
+    }
+
+    /// Returns true if no keys are held.
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    fn lookup(&self, key: &K, freshness: Freshness) -> Option<Cached<V>> {
+        let entries = self.entries.lock().unwrap();
+        let entry = entries.get(key)?;
+        Some(Cached {
+            value: entry.value.clone(),
+            freshness,
+            age: entry.stored_at.elapsed(),
+        })
+    }
+}
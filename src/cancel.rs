@@ -0,0 +1,176 @@
+//! Cooperative cancellation of retry sequences.
+//!
+//! Dropping the future returned by [`RetryPolicy::retry`] stops it, but loses the last error
+//! and any record of what happened. [`RetryPolicy::retry_with_cancel`] instead watches a
+//! cancellation token: cancelling interrupts the backoff sleep immediately and returns
+//! [`RetryError::Cancelled`] with the last error and the attempt stats.
+//!
+//! [`CancelToken`] is a small crate-local token; with the `tokio-util` feature,
+//! `tokio_util::sync::CancellationToken` can be used as well.
+
+use crate::{Halt, RetryError, RetryHooks, RetryPolicy};
+use std::future::Future;
+use std::panic::Location;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::Duration;
+use tokio::sync::Notify;
+
+/// A token that signals cancellation.
+pub trait CancelSignal {
+    /// Returns true once cancellation was requested.
+    fn is_cancelled(&self) -> bool;
+
+    /// Resolve once cancellation is requested.
+    fn cancelled(&self) -> impl Future<Output = ()> + Send + '_;
+}
+
+#[derive(Debug, Default)]
+struct TokenState {
+    cancelled: AtomicBool,
+    notify: Notify,
+}
+
+/// Crate-local cancellation token; clones share the same state.
+#[derive(Clone, Debug, Default)]
+pub struct CancelToken {
+    state: Arc<TokenState>,
+}
+
+impl CancelToken {
+    /// Create a token that is not cancelled.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Request cancellation, waking every sequence waiting on this token.
+    pub fn cancel(&self) {
+        self.state.cancelled.store(true, Ordering::SeqCst);
+        self.state.notify.notify_waiters();
+    }
+}
+
+impl CancelSignal for CancelToken {
+    fn is_cancelled(&self) -> bool {
+        self.state.cancelled.load(Ordering::SeqCst)
+    }
+
+    async fn cancelled(&self) {
+        loop {
+            let notified = self.state.notify.notified();
+            if self.is_cancelled() {
+                return;
+            }
+            notified.await;
+        }
+    }
+}
+
+#[cfg(feature = "tokio-util")]
+impl CancelSignal for tokio_util::sync::CancellationToken {
+    fn is_cancelled(&self) -> bool {
+        tokio_util::sync::CancellationToken::is_cancelled(self)
+    }
+
+    fn cancelled(&self) -> impl Future<Output = ()> + Send + '_ {
+        tokio_util::sync::CancellationToken::cancelled(self)
+    }
+}
+
+/// A cancellation token together with how an in-flight attempt is treated.
+#[derive(Debug)]
+pub struct CancelOn<'a, C> {
+    token: &'a C,
+    finish_in_flight: bool,
+}
+
+impl<'a, C: CancelSignal> CancelOn<'a, C> {
+    /// Cancel on `token`, dropping an attempt that is in flight when cancellation happens.
+    pub fn new(token: &'a C) -> Self {
+        Self {
+            token,
+            finish_in_flight: false,
+        }
+    }
+
+    /// Let an in-flight attempt finish; if it succeeds its value is returned, otherwise its
+    /// error becomes the `last_error` of the cancellation.
+    pub fn finish_in_flight(mut self) -> Self {
+        self.finish_in_flight = true;
+        self
+    }
+}
+
+impl<'a, C: CancelSignal> From<&'a C> for CancelOn<'a, C> {
+    fn from(token: &'a C) -> Self {
+        Self::new(token)
+    }
+}
+
+/// Marker for a sequence stopped by its cancellation token.
+pub(crate) struct Cancelled;
+
+impl<C: CancelSignal> RetryHooks for CancelOn<'_, C> {
+    type Interrupt = Cancelled;
+
+    async fn attempt<Fut: Future>(&mut self, attempt: Fut) -> Result<Fut::Output, Cancelled> {
+        if self.token.is_cancelled() {
+            return Err(Cancelled);
+        }
+        if self.finish_in_flight {
+            return Ok(attempt.await);
+        }
+        tokio::select! {
+            biased;
+            _ = self.token.cancelled() => Err(Cancelled),
+            output = attempt => Ok(output),
+        }
+    }
+
+    async fn wait(&mut self, delay: Duration) -> Result<(), Cancelled> {
+        tokio::select! {
+            biased;
+            _ = self.token.cancelled() => Err(Cancelled),
+            _ = tokio::time::sleep(delay) => Ok(()),
+        }
+    }
+}
+
+impl RetryPolicy {
+    /// Like [`RetryPolicy::retry`], but stops as soon as `cancel` is cancelled.
+    ///
+    /// `cancel` is a token implementing [`CancelSignal`] or a [`CancelOn`] configuring whether
+    /// an in-flight attempt is dropped (the default) or allowed to finish. A cancelled sequence
+    /// returns [`RetryError::Cancelled`] carrying the last error and the stats so far; an
+    /// exhausted one returns [`RetryError::Failed`].
+    #[track_caller]
+    pub fn retry_with_cancel<'a, Fut, T, E, F, P, C>(
+        &self,
+        f: F,
+        should_retry: P,
+        cancel: impl Into<CancelOn<'a, C>>,
+    ) -> impl Future<Output = Result<T, RetryError<E>>>
+    where
+        F: FnMut() -> Fut,
+        Fut: Future<Output = Result<T, E>>,
+        P: FnMut(&E) -> bool,
+        C: CancelSignal + 'a,
+    {
+        let site = Location::caller();
+        async move {
+            match self
+                .run_hooked(f, should_retry, site, &mut cancel.into())
+                .await
+            {
+                Ok((v, _)) => Ok(v),
+                Err((Halt::Failed(e), _)) => Err(RetryError::Failed(e)),
+                Err((Halt::Interrupted(Cancelled, last_error), stats)) => {
+                    Err(RetryError::Cancelled { last_error, stats })
+                }
+                Err((Halt::ShuttingDown(last_error), stats)) => {
+                    Err(RetryError::ShuttingDown { last_error, stats })
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,108 @@
+//! Task-local context of the running retry sequences.
+//!
+//! While an attempt runs, [`RetryContext::current`] describes the innermost enclosing retry
+//! sequence: its nesting depth and the deadline inherited from every enclosing policy's
+//! `max_elapsed`. Nested retries (including `#[retry]` functions called from a retried
+//! operation) clamp their own deadline to the outer one, so they never back off past it; the
+//! operation itself can read the remaining budget, e.g. to size a request timeout.
+//!
+//! The context is task-local: it does not propagate into tasks spawned from an attempt.
+
+use crate::RetryPolicy;
+use std::future::Future;
+use std::panic::Location;
+use std::time::Duration;
+use tokio::time::Instant;
+
+tokio::task_local! {
+    static CONTEXT: RetryContext;
+}
+
+/// The retry sequence an attempt is running in.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct RetryContext {
+    deadline: Option<Instant>,
+    depth: usize,
+    site: &'static Location<'static>,
+    total_attempts: usize,
+}
+
+impl RetryContext {
+    /// The context of the innermost retry sequence on this task, if inside an attempt.
+    pub fn current() -> Option<RetryContext> {
+        CONTEXT.try_with(|ctx| *ctx).ok()
+    }
+
+    /// When the sequence, including every enclosing one, runs out of time.
+    pub fn deadline(&self) -> Option<Instant> {
+        self.deadline
+    }
+
+    /// Time left until the deadline; `None` without a deadline.
+    pub fn remaining(&self) -> Option<Duration> {
+        self.deadline
+            .map(|deadline| deadline.saturating_duration_since(Instant::now()))
+    }
+
+    /// Nesting depth of the sequence: 1 for the outermost retry, 2 for a retry inside it, …
+    pub fn depth(&self) -> usize {
+        self.depth
+    }
+
+    /// Where the sequence was started.
+    pub(crate) fn site(&self) -> &'static Location<'static> {
+        self.site
+    }
+
+    /// Product of the attempts of this sequence and every enclosing one.
+    pub(crate) fn total_attempts(&self) -> usize {
+        self.total_attempts
+    }
+
+    /// Enter a new sequence of `policy` started at `site` at time `start`.
+    ///
+    /// Returns the context together with the attempts the sequence may make, which are
+    /// limited by the policy's [`NestedRetries`](crate::NestedRetries) behavior when nested.
+    pub(crate) fn enter(
+        policy: &RetryPolicy,
+        start: Instant,
+        site: &'static Location<'static>,
+    ) -> (RetryContext, usize) {
+        // Always make at least one attempt, even with `attempts = 0`
+        let mut attempts = policy.attempts.max(1);
+        let outer = Self::current();
+        if let Some(outer) = &outer {
+            attempts = policy.on_nested.attempts(outer, site, attempts);
+        }
+        let own = policy.max_elapsed.map(|budget| start + budget);
+        let deadline = match (own, outer.and_then(|ctx| ctx.deadline)) {
+            (Some(own), Some(outer)) => Some(own.min(outer)),
+            (own, outer) => own.or(outer),
+        };
+        let context = RetryContext {
+            deadline,
+            depth: outer.map_or(0, |ctx| ctx.depth) + 1,
+            site,
+            total_attempts: outer
+                .map_or(1, |ctx| ctx.total_attempts)
+                .saturating_mul(attempts),
+        };
+        (context, attempts)
+    }
+
+    /// Start an attempt with this context as the current one, both while `f` creates the
+    /// future and while it runs.
+    pub(crate) fn scope<Fut: Future>(
+        self,
+        f: impl FnOnce() -> Fut,
+    ) -> impl Future<Output = Fut::Output> {
+        CONTEXT.scope(self, CONTEXT.sync_scope(self, f))
+    }
+}
+
+/// Time left of the innermost retry sequence's budget, from inside an attempt.
+///
+/// `None` outside of an attempt or when no enclosing policy sets `max_elapsed`.
+pub fn remaining_budget() -> Option<Duration> {
+    RetryContext::current().and_then(|ctx| ctx.remaining())
+}
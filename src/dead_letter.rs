@@ -0,0 +1,352 @@
+//! Dead-letter sinks for exhausted retry sequences.
+//!
+//! When a retry sequence exhausts its policy, [`RetryPolicy::retry_or_dead_letter`] records the
+//! failing payload and its context (policy name, attempts, errors, timestamps) in a pluggable
+//! [`DeadLetterSink`] instead of letting it disappear into the caller's error handling.
+//! [`replay`] re-drives recorded dead letters through a policy later.
+
+use crate::{Halt, RetryPolicy, Sleep};
+use async_trait::async_trait;
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+use std::fmt::Display;
+use std::fs::{File, OpenOptions};
+use std::future::Future;
+use std::io::{self, BufRead, BufReader, Write};
+use std::marker::PhantomData;
+use std::panic::Location;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use std::time::SystemTime;
+use tokio::sync::mpsc;
+
+/// A payload whose retry sequence was exhausted, with the context of the failure.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct DeadLetter<P> {
+    /// The payload the operation was driven with
+    pub payload: P,
+    /// `RetryPolicy::name` of the policy that gave up
+    pub policy: Option<String>,
+    /// Number of attempts that were made
+    pub attempts: usize,
+    /// Every attempt's error, formatted with `Display`, oldest first
+    pub errors: Vec<String>,
+    /// When the first attempt failed
+    pub first_failure: SystemTime,
+    /// When the last attempt failed
+    pub last_failure: SystemTime,
+}
+
+/// Errors raised while recording a dead letter.
+#[derive(Debug, thiserror::Error)]
+pub enum DeadLetterError {
+    /// Writing to the underlying storage failed
+    #[error("failed to write dead letter: {0}")]
+    Io(#[from] io::Error),
+    /// The dead letter could not be serialized
+    #[error("failed to serialize dead letter: {0}")]
+    Serialize(#[from] serde_json::Error),
+    /// The receiving side of a channel sink was dropped
+    #[error("dead-letter channel is closed")]
+    Closed,
+}
+
+/// Error returned by [`RetryPolicy::retry_or_dead_letter`].
+#[derive(Debug, thiserror::Error)]
+pub enum DeadLetterRetryError<E, P> {
+    /// The policy was exhausted and the payload was recorded as a dead letter
+    #[error("{0}")]
+    Recorded(E),
+    /// The sequence stopped before the policy was exhausted, because the predicate rejected the
+    /// error or a shutdown interrupted it; nothing was recorded
+    #[error("{0}")]
+    Stopped(E),
+    /// The policy was exhausted but the sink failed to record the dead letter, which is
+    /// returned here instead
+    #[error("{error} (dead letter not recorded: {source})")]
+    NotRecorded {
+        /// Error of the last attempt
+        error: E,
+        /// The dead letter the sink failed to record
+        letter: DeadLetter<P>,
+        /// Why recording failed
+        source: DeadLetterError,
+    },
+}
+
+impl<E, P> DeadLetterRetryError<E, P> {
+    /// The error of the last attempt.
+    pub fn error(&self) -> &E {
+        match self {
+            Self::Recorded(e) | Self::Stopped(e) | Self::NotRecorded { error: e, .. } => e,
+        }
+    }
+
+    /// Consume the error, returning the error of the last attempt.
+    pub fn into_error(self) -> E {
+        match self {
+            Self::Recorded(e) | Self::Stopped(e) | Self::NotRecorded { error: e, .. } => e,
+        }
+    }
+}
+
+/// Destination for dead letters.
+#[async_trait]
+pub trait DeadLetterSink<P: Send + 'static>: Send + Sync {
+    /// Store a dead letter.
+    async fn record(&self, letter: DeadLetter<P>) -> Result<(), DeadLetterError>;
+}
+
+/// Keeps dead letters in memory, e.g. for tests or a later in-process replay.
+#[derive(Debug)]
+pub struct MemoryDeadLetterSink<P> {
+    letters: Mutex<Vec<DeadLetter<P>>>,
+}
+
+impl<P> Default for MemoryDeadLetterSink<P> {
+    fn default() -> Self {
+        Self {
+            letters: Mutex::new(Vec::new()),
+        }
+    }
+}
+
+impl<P> MemoryDeadLetterSink<P> {
+    /// Create an empty sink.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Remove and return every recorded dead letter.
+    pub fn drain(&self) -> Vec<DeadLetter<P>> {
+        std::mem::take(&mut *self.letters.lock().unwrap())
+    }
+
+    /// Number of recorded dead letters.
+    pub fn len(&self) -> usize {
+        self.letters.lock().unwrap().len()
+    }
+
+    /// Returns true if nothing was recorded.
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
+
+#[async_trait]
+impl<P: Send + 'static> DeadLetterSink<P> for MemoryDeadLetterSink<P> {
+    async fn record(&self, letter: DeadLetter<P>) -> Result<(), DeadLetterError> {
+        self.letters.lock().unwrap().push(letter);
+        Ok(())
+    }
+}
+
+/// Appends dead letters to a file, one JSON document per line.
+#[derive(Debug)]
+pub struct FileDeadLetterSink<P> {
+    path: PathBuf,
+    file: Mutex<Option<File>>,
+    _payload: PhantomData<fn(P)>,
+}
+
+impl<P> FileDeadLetterSink<P> {
+    /// Append to the JSON-lines file at `path`, creating it on the first dead letter.
+    pub fn new(path: impl AsRef<Path>) -> Self {
+        Self {
+            path: path.as_ref().to_path_buf(),
+            file: Mutex::new(None),
+            _payload: PhantomData,
+        }
+    }
+
+    /// Read back every dead letter stored in the file.
+    pub fn read_all(&self) -> Result<Vec<DeadLetter<P>>, DeadLetterError>
+    where
+        P: DeserializeOwned,
+    {
+        if !self.path.exists() {
+            return Ok(Vec::new());
+        }
+        let mut letters = Vec::new();
+        for line in BufReader::new(File::open(&self.path)?).lines() {
+            letters.push(serde_json::from_str(&line?)?);
+        }
+        Ok(letters)
+    }
+}
+
+#[async_trait]
+impl<P: Serialize + Send + 'static> DeadLetterSink<P> for FileDeadLetterSink<P> {
+    async fn record(&self, letter: DeadLetter<P>) -> Result<(), DeadLetterError> {
+        let mut line = serde_json::to_vec(&letter)?;
+        line.push(b'\n');
+        let mut file = self.file.lock().unwrap();
+        let file = match &mut *file {
+            Some(file) => file,
+            None => file.insert(
+                OpenOptions::new()
+                    .create(true)
+                    .append(true)
+                    .open(&self.path)?,
+            ),
+        };
+        file.write_all(&line)?;
+        file.sync_data()?;
+        Ok(())
+    }
+}
+
+/// Forwards dead letters to a channel, e.g. to a task that alerts or persists them.
+#[derive(Debug)]
+pub struct ChannelDeadLetterSink<P> {
+    tx: mpsc::Sender<DeadLetter<P>>,
+}
+
+impl<P> ChannelDeadLetterSink<P> {
+    /// Create a sink and the receiver its dead letters are delivered to.
+    pub fn channel(buffer: usize) -> (Self, mpsc::Receiver<DeadLetter<P>>) {
+        let (tx, rx) = mpsc::channel(buffer);
+        (Self { tx }, rx)
+    }
+}
+
+#[async_trait]
+impl<P: Send + 'static> DeadLetterSink<P> for ChannelDeadLetterSink<P> {
+    async fn record(&self, letter: DeadLetter<P>) -> Result<(), DeadLetterError> {
+        self.tx
+            .send(letter)
+            .await
+            .map_err(|_| DeadLetterError::Closed)
+    }
+}
+
+impl RetryPolicy {
+    /// Retry `f` with `payload` and record a [`DeadLetter`] in `sink` once the policy is
+    /// exhausted.
+    ///
+    /// `f` is called with a clone of `payload` on every attempt. Errors the predicate rejects
+    /// and shutdown interruptions are not exhausted retries: they are returned as
+    /// [`DeadLetterRetryError::Stopped`] without recording anything. If the sink fails, the
+    /// dead letter is handed back in [`DeadLetterRetryError::NotRecorded`].
+    #[track_caller]
+    pub fn retry_or_dead_letter<Pl, Fut, T, E, F, P, S>(
+        &self,
+        payload: Pl,
+        mut f: F,
+        mut should_retry: P,
+        sink: &S,
+    ) -> impl Future<Output = Result<T, DeadLetterRetryError<E, Pl>>>
+    where
+        Pl: Clone + Send + 'static,
+        F: FnMut(Pl) -> Fut,
+        Fut: Future<Output = Result<T, E>>,
+        E: Display,
+        P: FnMut(&E) -> bool,
+        S: DeadLetterSink<Pl> + ?Sized,
+    {
+        let site = Location::caller();
+        async move {
+            let failures = Mutex::new(Vec::new());
+            let mut rejected = false;
+            let result = self
+                .run_hooked(
+                    || {
+                        let failures = &failures;
+                        let attempt = f(payload.clone());
+                        async move {
+                            let res = attempt.await;
+                            if let Err(e) = &res {
+                                failures
+                                    .lock()
+                                    .unwrap()
+                                    .push((SystemTime::now(), e.to_string()));
+                            }
+                            res
+                        }
+                    },
+                    |e: &E| {
+                        let retry = should_retry(e);
+                        rejected = !retry;
+                        retry
+                    },
+                    site,
+                    &mut Sleep,
+                )
+                .await;
+
+            match result {
+                Ok((v, _)) => Ok(v),
+                Err((Halt::ShuttingDown(e), _)) => Err(DeadLetterRetryError::Stopped(e)),
+                Err((Halt::Interrupted(never, _), _)) => match never {},
+                Err((Halt::Failed(e), _)) if rejected => Err(DeadLetterRetryError::Stopped(e)),
+                Err((Halt::Failed(e), stats)) => {
+                    let failures = failures.into_inner().unwrap();
+                    let letter = DeadLetter {
+                        payload,
+                        policy: self.name.map(str::to_owned),
+                        attempts: stats.attempts,
+                        first_failure: failures.first().map_or_else(SystemTime::now, |f| f.0),
+                        last_failure: failures.last().map_or_else(SystemTime::now, |f| f.0),
+                        errors: failures.into_iter().map(|(_, e)| e).collect(),
+                    };
+                    match sink.record(letter.clone()).await {
+                        Ok(()) => Err(DeadLetterRetryError::Recorded(e)),
+                        Err(source) => Err(DeadLetterRetryError::NotRecorded {
+                            error: e,
+                            letter,
+                            source,
+                        }),
+                    }
+                }
+            }
+        }
+    }
+}
+
+/// Outcome of [`replay`].
+#[derive(Clone, Debug, PartialEq)]
+pub struct ReplayReport<P> {
+    /// Dead letters whose payload now succeeded
+    pub succeeded: usize,
+    /// Dead letters that failed again and were recorded in the sink anew
+    pub failed: usize,
+    /// Dead letters that failed again without being recorded anew (the predicate rejected the
+    /// error, a shutdown interrupted the sequence or the sink failed), handed back so they
+    /// are not lost
+    pub unrecorded: Vec<DeadLetter<P>>,
+}
+
+/// Re-drive `letters` through `policy`, recording the ones that fail again in `sink`.
+pub async fn replay<Pl, Fut, T, E, F, P, S>(
+    policy: &RetryPolicy,
+    letters: impl IntoIterator<Item = DeadLetter<Pl>>,
+    mut f: F,
+    mut should_retry: P,
+    sink: &S,
+) -> ReplayReport<Pl>
+where
+    Pl: Clone + Send + 'static,
+    F: FnMut(Pl) -> Fut,
+    Fut: Future<Output = Result<T, E>>,
+    E: Display,
+    P: FnMut(&E) -> bool,
+    S: DeadLetterSink<Pl> + ?Sized,
+{
+    let mut report = ReplayReport {
+        succeeded: 0,
+        failed: 0,
+        unrecorded: Vec::new(),
+    };
+    for letter in letters {
+        match policy
+            .retry_or_dead_letter(letter.payload.clone(), &mut f, &mut should_retry, sink)
+            .await
+        {
+            Ok(_) => report.succeeded += 1,
+            Err(DeadLetterRetryError::Recorded(_)) => report.failed += 1,
+            Err(DeadLetterRetryError::Stopped(_)) => report.unrecorded.push(letter),
+            Err(DeadLetterRetryError::NotRecorded { letter, .. }) => report.unrecorded.push(letter),
+        }
+    }
+    report
+}
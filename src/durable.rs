@@ -0,0 +1,308 @@
+//! Durable retry queue persisted to local disk.
+//!
+//! Jobs that must eventually run even if the process restarts mid-backoff (webhook deliveries,
+//! outbox flushes) are stored in an append-only JSON-lines log together with their attempt
+//! count and next due time. The [`RetryPolicy`] backoff schedule is driven from that persisted
+//! state, so a worker started after a restart picks up exactly where the previous one stopped.
+//! Jobs that exhaust the policy are appended to a dead-letter file.
+
+use crate::RetryPolicy;
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fmt::Display;
+use std::fs::{File, OpenOptions};
+use std::future::Future;
+use std::io::{self, BufRead, BufReader, Write};
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use std::time::{Duration, SystemTime};
+use tokio::sync::Notify;
+
+/// A persisted job.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Job<J> {
+    /// Queue-assigned identifier
+    pub id: u64,
+    /// The job payload
+    pub payload: J,
+    /// Number of failed attempts so far
+    pub attempts: usize,
+    /// When the job should be attempted next
+    pub next_due: SystemTime,
+}
+
+/// A job that exhausted the policy, as written to the dead-letter file.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct DeadJob<J> {
+    /// The job in its final state
+    pub job: Job<J>,
+    /// The last error, formatted with `Display`
+    pub error: String,
+}
+
+/// Counts reported by [`DurableRetryQueue::run_due`].
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct RunReport {
+    /// Jobs that succeeded and were removed
+    pub succeeded: usize,
+    /// Jobs that failed and were rescheduled
+    pub rescheduled: usize,
+    /// Jobs that were moved to the dead-letter file
+    pub dead_lettered: usize,
+}
+
+#[derive(Serialize, Deserialize)]
+#[serde(tag = "op", rename_all = "snake_case")]
+enum Record<J> {
+    Put(Job<J>),
+    Done { id: u64 },
+}
+
+struct Inner<J> {
+    jobs: BTreeMap<u64, Job<J>>,
+    next_id: u64,
+    log: File,
+    log_records: usize,
+}
+
+/// A retry queue whose jobs survive process restarts.
+///
+/// Only `attempts` and the backoff fields of the policy apply. A job's schedule lives in the
+/// log rather than in a running sequence, so `max_elapsed`, `shutdown` and `on_nested` are
+/// ignored.
+pub struct DurableRetryQueue<J> {
+    path: PathBuf,
+    dead_letter_path: PathBuf,
+    policy: RetryPolicy,
+    inner: Mutex<Inner<J>>,
+    enqueued: Notify,
+}
+
+impl<J> DurableRetryQueue<J>
+where
+    J: Serialize + DeserializeOwned + Clone,
+{
+    /// Open (or create) the queue stored at `path`, replaying any jobs persisted earlier.
+    ///
+    /// Exhausted jobs are appended to `dead_letter_path` as JSON lines. The log is compacted
+    /// on open.
+    pub fn open(
+        path: impl AsRef<Path>,
+        dead_letter_path: impl AsRef<Path>,
+        policy: RetryPolicy,
+    ) -> io::Result<Self> {
+        let path = path.as_ref().to_path_buf();
+        let mut jobs = BTreeMap::new();
+        let mut next_id = 0;
+        if path.exists() {
+            let lines: Vec<String> = BufReader::new(File::open(&path)?)
+                .lines()
+                .collect::<io::Result<_>>()?;
+            let last = lines.len().saturating_sub(1);
+            for (i, line) in lines.iter().enumerate() {
+                match serde_json::from_str::<Record<J>>(line) {
+                    Ok(Record::Put(job)) => {
+                        next_id = next_id.max(job.id + 1);
+                        jobs.insert(job.id, job);
+                    }
+                    Ok(Record::Done { id }) => {
+                        jobs.remove(&id);
+                    }
+                    // a torn final line from a crash mid-write is ignored
+                    Err(_) if i == last => {}
+                    Err(e) => return Err(io::Error::new(io::ErrorKind::InvalidData, e)),
+                }
+            }
+        }
+
+        let log = OpenOptions::new().create(true).append(true).open(&path)?;
+        let queue = Self {
+            path,
+            dead_letter_path: dead_letter_path.as_ref().to_path_buf(),
+            policy,
+            inner: Mutex::new(Inner {
+                jobs,
+                next_id,
+                log,
+                log_records: 0,
+            }),
+            enqueued: Notify::new(),
+        };
+        queue.compact()?;
+        Ok(queue)
+    }
+
+    /// Persist a new job, due immediately, and return its id.
+    pub fn enqueue(&self, payload: J) -> io::Result<u64> {
+        let mut inner = self.inner.lock().unwrap();
+        let job = Job {
+            id: inner.next_id,
+            payload,
+            attempts: 0,
+            next_due: SystemTime::now(),
+        };
+        inner.next_id += 1;
+        let id = job.id;
+        Self::append(&mut inner, &Record::Put(job.clone()))?;
+        inner.jobs.insert(id, job);
+        drop(inner);
+        self.enqueued.notify_one();
+        Ok(id)
+    }
+
+    /// Snapshot of the pending jobs, ordered by id.
+    pub fn jobs(&self) -> Vec<Job<J>> {
+        self.inner.lock().unwrap().jobs.values().cloned().collect()
+    }
+
+    /// Number of pending jobs.
+    pub fn len(&self) -> usize {
+        self.inner.lock().unwrap().jobs.len()
+    }
+
+    /// Returns true if no jobs are pending.
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Attempt every job that is due now once.
+    ///
+    /// Successful jobs are removed. Failed jobs are rescheduled using the policy backoff, or
+    /// moved to the dead-letter file once `policy.attempts` is reached or `should_retry`
+    /// rejects the error.
+    pub async fn run_due<F, Fut, E, P>(
+        &self,
+        mut f: F,
+        mut should_retry: P,
+    ) -> io::Result<RunReport>
+    where
+        F: FnMut(J) -> Fut,
+        Fut: Future<Output = Result<(), E>>,
+        E: Display,
+        P: FnMut(&E) -> bool,
+    {
+        let now = SystemTime::now();
+        let due: Vec<Job<J>> = {
+            let inner = self.inner.lock().unwrap();
+            inner
+                .jobs
+                .values()
+                .filter(|job| job.next_due <= now)
+                .cloned()
+                .collect()
+        };
+
+        let mut report = RunReport::default();
+        for mut job in due {
+            let result = f(job.payload.clone()).await;
+            let mut inner = self.inner.lock().unwrap();
+            match result {
+                Ok(()) => {
+                    Self::append(&mut inner, &Record::Done { id: job.id })?;
+                    inner.jobs.remove(&job.id);
+                    report.succeeded += 1;
+                }
+                Err(e) => {
+                    job.attempts += 1;
+                    if job.attempts < self.policy.attempts && should_retry(&e) {
+                        job.next_due = SystemTime::now() + self.policy.jittered_delay(job.attempts);
+                        Self::append(&mut inner, &Record::Put(job.clone()))?;
+                        inner.jobs.insert(job.id, job);
+                        report.rescheduled += 1;
+                    } else {
+                        let id = job.id;
+                        self.dead_letter(DeadJob {
+                            job,
+                            error: e.to_string(),
+                        })?;
+                        Self::append(&mut inner, &Record::Done { id })?;
+                        inner.jobs.remove(&id);
+                        report.dead_lettered += 1;
+                    }
+                }
+            }
+        }
+
+        let needs_compaction = {
+            let inner = self.inner.lock().unwrap();
+            inner.log_records > 64 && inner.log_records > 2 * inner.jobs.len()
+        };
+        if needs_compaction {
+            self.compact()?;
+        }
+        Ok(report)
+    }
+
+    /// Run due jobs until `shutdown` completes, sleeping until the next job is due or a new
+    /// job is enqueued.
+    pub async fn run<F, Fut, E, P, S>(
+        &self,
+        mut f: F,
+        mut should_retry: P,
+        shutdown: S,
+    ) -> io::Result<()>
+    where
+        F: FnMut(J) -> Fut,
+        Fut: Future<Output = Result<(), E>>,
+        E: Display,
+        P: FnMut(&E) -> bool,
+        S: Future<Output = ()>,
+    {
+        tokio::pin!(shutdown);
+        loop {
+            self.run_due(&mut f, &mut should_retry).await?;
+            let next_due = {
+                let inner = self.inner.lock().unwrap();
+                inner.jobs.values().map(|job| job.next_due).min()
+            };
+            // with nothing pending, wait for an enqueue (or shutdown)
+            let wait = next_due.map_or(Duration::MAX, |due| {
+                due.duration_since(SystemTime::now()).unwrap_or_default()
+            });
+            tokio::select! {
+                _ = tokio::time::sleep(wait.min(Duration::from_secs(3600))) => {}
+                _ = self.enqueued.notified() => {}
+                _ = &mut shutdown => return Ok(()),
+            }
+        }
+    }
+
+    /// Rewrite the log so it only contains the pending jobs.
+    pub fn compact(&self) -> io::Result<()> {
+        let mut inner = self.inner.lock().unwrap();
+        let tmp = self.path.with_extension("compact");
+        {
+            let mut file = File::create(&tmp)?;
+            for job in inner.jobs.values() {
+                serde_json::to_writer(&mut file, &Record::Put(job.clone()))?;
+                file.write_all(b"\n")?;
+            }
+            file.sync_all()?;
+        }
+        std::fs::rename(&tmp, &self.path)?;
+        inner.log = OpenOptions::new().append(true).open(&self.path)?;
+        inner.log_records = inner.jobs.len();
+        Ok(())
+    }
+
+    fn append(inner: &mut Inner<J>, record: &Record<J>) -> io::Result<()> {
+        let mut line = serde_json::to_vec(record)?;
+        line.push(b'\n');
+        inner.log.write_all(&line)?;
+        inner.log.sync_data()?;
+        inner.log_records += 1;
+        Ok(())
+    }
+
+    fn dead_letter(&self, dead: DeadJob<J>) -> io::Result<()> {
+        let mut line = serde_json::to_vec(&dead)?;
+        line.push(b'\n');
+        let mut file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&self.dead_letter_path)?;
+        file.write_all(&line)?;
+        file.sync_data()
+    }
+}
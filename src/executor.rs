@@ -0,0 +1,312 @@
+//! Central retry executor with a concurrency limit and due-time scheduling.
+//!
+//! Instead of every operation sleeping inside its own `retry` loop, a [`RetryExecutor`] keeps
+//! pending retries in a priority queue ordered by due time and runs attempts on a bounded
+//! number of workers. Results are handed back through [`RetryTicket`] futures. The executor is
+//! the single place to pause, drain or inspect pending retries.
+
+use crate::RetryPolicy;
+use futures::future::BoxFuture;
+use std::cmp::{Ordering, Reverse};
+use std::collections::BinaryHeap;
+use std::future::Future;
+use std::pin::Pin;
+use std::sync::{Arc, Mutex};
+use std::task::{Context, Poll};
+use tokio::sync::{Notify, Semaphore, oneshot};
+use tokio::time::Instant;
+
+/// What to do with a submission after one attempt.
+enum Next {
+    Done,
+    Retry(Box<dyn Attempt>, std::time::Duration),
+}
+
+/// A type-erased submission that can run its next attempt.
+trait Attempt: Send {
+    fn attempt(self: Box<Self>) -> BoxFuture<'static, Next>;
+    fn is_abandoned(&self) -> bool;
+}
+
+struct Submission<F, P, T, E> {
+    policy: RetryPolicy,
+    f: F,
+    should_retry: P,
+    attempt: usize,
+    tx: oneshot::Sender<Result<T, E>>,
+}
+
+impl<F, Fut, P, T, E> Attempt for Submission<F, P, T, E>
+where
+    F: FnMut() -> Fut + Send + 'static,
+    Fut: Future<Output = Result<T, E>> + Send + 'static,
+    P: FnMut(&E) -> bool + Send + 'static,
+    T: Send + 'static,
+    E: Send + 'static,
+{
+    fn attempt(mut self: Box<Self>) -> BoxFuture<'static, Next> {
+        Box::pin(async move {
+            self.attempt += 1;
+            match (self.f)().await {
+                Err(e) if self.attempt < self.policy.attempts && (self.should_retry)(&e) => {
+                    let delay = self.policy.jittered_delay(self.attempt);
+                    Next::Retry(self, delay)
+                }
+                result => {
+                    let _ = self.tx.send(result);
+                    Next::Done
+                }
+            }
+        })
+    }
+
+    fn is_abandoned(&self) -> bool {
+        self.tx.is_closed()
+    }
+}
+
+struct Pending {
+    due: Instant,
+    seq: u64,
+    job: Box<dyn Attempt>,
+}
+
+impl PartialEq for Pending {
+    fn eq(&self, other: &Self) -> bool {
+        (self.due, self.seq) == (other.due, other.seq)
+    }
+}
+
+impl Eq for Pending {}
+
+impl PartialOrd for Pending {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for Pending {
+    fn cmp(&self, other: &Self) -> Ordering {
+        (self.due, self.seq).cmp(&(other.due, other.seq))
+    }
+}
+
+#[derive(Default)]
+struct State {
+    queue: BinaryHeap<Reverse<Pending>>,
+    seq: u64,
+    in_flight: usize,
+    paused: bool,
+    closed: bool,
+}
+
+struct Shared {
+    state: Mutex<State>,
+    workers: Arc<Semaphore>,
+    /// Wakes the scheduler when the queue or the paused flag changes
+    changed: Notify,
+    /// Wakes `drain` callers when work finishes
+    idle: Notify,
+}
+
+impl Shared {
+    fn push(&self, due: Instant, job: Box<dyn Attempt>) {
+        let mut state = self.state.lock().unwrap();
+        let seq = state.seq;
+        state.seq += 1;
+        state.queue.push(Reverse(Pending { due, seq, job }));
+        drop(state);
+        self.changed.notify_one();
+    }
+}
+
+/// Marks one attempt as running; dropping it, even while a panicking attempt unwinds,
+/// marks the attempt as finished.
+struct InFlight(Arc<Shared>);
+
+impl Drop for InFlight {
+    fn drop(&mut self) {
+        self.0.state.lock().unwrap().in_flight -= 1;
+        self.0.idle.notify_waiters();
+        self.0.changed.notify_one();
+    }
+}
+
+/// Runs retry attempts for many operations on a bounded worker pool.
+///
+/// Must be created inside a tokio runtime; the scheduler runs on a background task that stops
+/// when the executor is dropped.
+pub struct RetryExecutor {
+    shared: Arc<Shared>,
+}
+
+impl RetryExecutor {
+    /// Create an executor running at most `workers` attempts concurrently.
+    pub fn new(workers: usize) -> Self {
+        let shared = Arc::new(Shared {
+            state: Mutex::new(State::default()),
+            workers: Arc::new(Semaphore::new(workers.max(1))),
+            changed: Notify::new(),
+            idle: Notify::new(),
+        });
+        tokio::spawn(schedule(shared.clone()));
+        Self { shared }
+    }
+
+    /// Submit an operation to be retried with its own `policy`.
+    ///
+    /// The first attempt is scheduled immediately. Dropping the returned ticket abandons the
+    /// submission before its next attempt.
+    ///
+    /// Only `attempts` and the backoff fields of `policy` apply. Attempts are scheduled by the
+    /// executor rather than by a running sequence, so `max_elapsed`, `shutdown` and `on_nested`
+    /// are ignored; use [`RetryExecutor::pause`] and [`RetryExecutor::drain`] to stop work.
+    pub fn submit<F, Fut, P, T, E>(
+        &self,
+        policy: RetryPolicy,
+        f: F,
+        should_retry: P,
+    ) -> RetryTicket<T, E>
+    where
+        F: FnMut() -> Fut + Send + 'static,
+        Fut: Future<Output = Result<T, E>> + Send + 'static,
+        P: FnMut(&E) -> bool + Send + 'static,
+        T: Send + 'static,
+        E: Send + 'static,
+    {
+        let (tx, rx) = oneshot::channel();
+        let submission = Submission {
+            policy,
+            f,
+            should_retry,
+            attempt: 0,
+            tx,
+        };
+        self.shared.push(Instant::now(), Box::new(submission));
+        RetryTicket { rx }
+    }
+
+    /// Stop starting attempts; attempts already running finish normally.
+    pub fn pause(&self) {
+        self.shared.state.lock().unwrap().paused = true;
+    }
+
+    /// Resume starting attempts after [`RetryExecutor::pause`].
+    pub fn resume(&self) {
+        self.shared.state.lock().unwrap().paused = false;
+        self.shared.changed.notify_one();
+    }
+
+    /// Number of submissions waiting for their next attempt.
+    pub fn pending(&self) -> usize {
+        self.shared.state.lock().unwrap().queue.len()
+    }
+
+    /// Number of attempts currently running.
+    pub fn in_flight(&self) -> usize {
+        self.shared.state.lock().unwrap().in_flight
+    }
+
+    /// When the earliest pending attempt is due.
+    pub fn next_due(&self) -> Option<Instant> {
+        let state = self.shared.state.lock().unwrap();
+        state.queue.peek().map(|Reverse(pending)| pending.due)
+    }
+
+    /// Wait until no submissions are pending or running.
+    pub async fn drain(&self) {
+        loop {
+            let idle = self.shared.idle.notified();
+            {
+                let state = self.shared.state.lock().unwrap();
+                if state.queue.is_empty() && state.in_flight == 0 {
+                    return;
+                }
+            }
+            idle.await;
+        }
+    }
+}
+
+impl Drop for RetryExecutor {
+    fn drop(&mut self) {
+        self.shared.state.lock().unwrap().closed = true;
+        self.shared.changed.notify_one();
+    }
+}
+
+/// Resolves to the result of a submission.
+///
+/// Resolves to `None` if the executor was dropped before the submission finished, or if an
+/// attempt panicked.
+pub struct RetryTicket<T, E> {
+    rx: oneshot::Receiver<Result<T, E>>,
+}
+
+impl<T, E> Future for RetryTicket<T, E> {
+    type Output = Option<Result<T, E>>;
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        Pin::new(&mut self.rx).poll(cx).map(Result::ok)
+    }
+}
+
+/// Scheduler loop: start due attempts whenever a worker is free.
+async fn schedule(shared: Arc<Shared>) {
+    loop {
+        let changed = shared.changed.notified();
+        let next_due = {
+            let state = shared.state.lock().unwrap();
+            if state.closed {
+                return;
+            }
+            if state.paused {
+                None
+            } else {
+                state.queue.peek().map(|Reverse(pending)| pending.due)
+            }
+        };
+        match next_due {
+            None => {
+                changed.await;
+                continue;
+            }
+            Some(due) if due > Instant::now() => {
+                tokio::select! {
+                    _ = tokio::time::sleep_until(due) => {}
+                    _ = changed => {}
+                }
+                continue;
+            }
+            Some(_) => {}
+        }
+
+        let Ok(permit) = shared.workers.clone().acquire_owned().await else {
+            return;
+        };
+        let job = {
+            let mut state = shared.state.lock().unwrap();
+            if state.paused || state.closed {
+                continue;
+            }
+            let Some(Reverse(pending)) = state.queue.pop() else {
+                continue;
+            };
+            state.in_flight += 1;
+            pending.job
+        };
+
+        let in_flight = InFlight(shared.clone());
+        tokio::spawn(async move {
+            let next = if job.is_abandoned() {
+                Next::Done
+            } else {
+                job.attempt().await
+            };
+            drop(permit);
+            if let Next::Retry(job, delay) = next {
+                in_flight.0.push(Instant::now() + delay, job);
+            }
+        });
+    }
+}
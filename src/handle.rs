@@ -0,0 +1,144 @@
+//! Background retries with an observable, controllable handle.
+//!
+//! [`RetryPolicy::spawn`] runs the retry loop on a tokio task and returns a [`RetryHandle`]
+//! exposing the current attempt and the next scheduled retry (e.g. to show
+//! "reconnecting in 12s… [Retry now]"), plus `abort` and `retry_now`.
+
+use crate::{RetryHooks, RetryPolicy, uninterrupted};
+use std::convert::Infallible;
+use std::future::Future;
+use std::panic::Location;
+use std::pin::Pin;
+use std::sync::Arc;
+use std::task::{Context, Poll};
+use std::time::Duration;
+use tokio::sync::{Notify, watch};
+use tokio::task::{JoinError, JoinHandle};
+use tokio::time::Instant;
+
+/// Progress of a spawned retry sequence.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct RetryProgress {
+    /// The attempt currently running or most recently finished (1-based; 0 before the first)
+    pub attempt: usize,
+    /// When the next attempt is scheduled, while the sequence is backing off
+    pub next_retry: Option<Instant>,
+}
+
+/// Handle to a retry sequence running on a background task.
+///
+/// Awaiting the handle yields the sequence's result, or a [`JoinError`] if it was aborted or
+/// the operation panicked.
+pub struct RetryHandle<T, E> {
+    join: JoinHandle<Result<T, E>>,
+    progress: watch::Receiver<RetryProgress>,
+    retry_now: Arc<Notify>,
+}
+
+impl<T, E> RetryHandle<T, E> {
+    /// The current attempt number.
+    pub fn attempt(&self) -> usize {
+        self.progress.borrow().attempt
+    }
+
+    /// When the next attempt is scheduled, if the sequence is currently backing off.
+    pub fn next_retry(&self) -> Option<Instant> {
+        self.progress.borrow().next_retry
+    }
+
+    /// A receiver notified whenever the attempt or the scheduled retry time changes.
+    pub fn progress(&self) -> watch::Receiver<RetryProgress> {
+        self.progress.clone()
+    }
+
+    /// Skip the current backoff sleep and start the next attempt immediately.
+    ///
+    /// Has no effect while an attempt is running.
+    pub fn retry_now(&self) {
+        self.retry_now.notify_waiters();
+    }
+
+    /// Stop the sequence; awaiting the handle then yields a cancelled [`JoinError`].
+    pub fn abort(&self) {
+        self.join.abort();
+    }
+
+    /// Returns true once the sequence has finished.
+    pub fn is_finished(&self) -> bool {
+        self.join.is_finished()
+    }
+}
+
+impl<T, E> Future for RetryHandle<T, E> {
+    type Output = Result<Result<T, E>, JoinError>;
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        Pin::new(&mut self.join).poll(cx)
+    }
+}
+
+/// Publishes progress and lets `retry_now` cut the backoff short.
+struct Observed {
+    progress: watch::Sender<RetryProgress>,
+    retry_now: Arc<Notify>,
+}
+
+impl RetryHooks for Observed {
+    type Interrupt = Infallible;
+
+    fn before_attempt(&mut self, attempt: usize) {
+        self.progress.send_replace(RetryProgress {
+            attempt,
+            next_retry: None,
+        });
+    }
+
+    async fn wait(&mut self, delay: Duration) -> Result<(), Infallible> {
+        let deadline = Instant::now() + delay;
+        // listen before publishing `next_retry`, so a click reacting to it can't be missed
+        let retry_now = self.retry_now.notified();
+        tokio::pin!(retry_now);
+        retry_now.as_mut().enable();
+        self.progress.send_modify(|p| p.next_retry = Some(deadline));
+        tokio::select! {
+            _ = tokio::time::sleep_until(deadline) => {}
+            _ = retry_now => {}
+        }
+        Ok(())
+    }
+}
+
+impl RetryPolicy {
+    /// Run the retry loop for `f` on a background tokio task.
+    ///
+    /// The returned [`RetryHandle`] reports progress and can abort the sequence or skip the
+    /// current backoff sleep.
+    #[track_caller]
+    pub fn spawn<Fut, T, E, F, P>(&self, f: F, should_retry: P) -> RetryHandle<T, E>
+    where
+        F: FnMut() -> Fut + Send + 'static,
+        Fut: Future<Output = Result<T, E>> + Send + 'static,
+        T: Send + 'static,
+        E: Send + 'static,
+        P: FnMut(&E) -> bool + Send + 'static,
+    {
+        let (progress, rx) = watch::channel(RetryProgress::default());
+        let retry_now = Arc::new(Notify::new());
+        let mut hooks = Observed {
+            progress,
+            retry_now: retry_now.clone(),
+        };
+        let policy = self.clone();
+        let site = Location::caller();
+        let join = tokio::spawn(async move {
+            uninterrupted(policy.run_hooked(f, should_retry, site, &mut hooks).await)
+                .map(|(v, _)| v)
+                .map_err(|(e, _)| e)
+        });
+        RetryHandle {
+            join,
+            progress: rx,
+            retry_now,
+        }
+    }
+}
@@ -63,14 +63,67 @@
 //! }
 //! ```
 
+pub mod backoff;
+mod batch;
+pub mod cache;
+pub mod cancel;
+pub mod context;
+pub mod dead_letter;
+pub mod durable;
+pub mod executor;
+pub mod handle;
+pub mod nesting;
+mod panic;
+pub mod poll;
+pub mod predicate;
+pub mod ready;
+mod reborrow;
+mod retryable;
+pub mod saga;
+pub mod shutdown;
+pub mod sink;
+pub mod stream;
+pub mod supervisor;
+
+pub use backoff::BackoffState;
+pub use cache::{Cached, Freshness, StaleCache};
+pub use cancel::{CancelOn, CancelSignal, CancelToken};
+pub use context::{RetryContext, remaining_budget};
+pub use dead_letter::{DeadLetter, DeadLetterError, DeadLetterRetryError, DeadLetterSink};
+pub use durable::DurableRetryQueue;
+pub use executor::{RetryExecutor, RetryTicket};
+pub use handle::{RetryHandle, RetryProgress};
+pub use nesting::{NestedRetries, NestedRetry, set_nested_retry_handler};
+pub use panic::AttemptError;
+pub use poll::{PollError, poll_until};
+pub use predicate::RetryPredicate;
+pub use ready::{ReadyGate, ReadySignal};
+pub use retryable::{IsRetryable, Retryable};
+pub use saga::{Compensation, Saga, SagaError};
+pub use shutdown::{ShutdownCoordinator, ShutdownReport};
+pub use sink::RetrySink;
+pub use stream::{ItemOutcome, RetryStreamExt, retry_stream};
+pub use supervisor::{StopReason, Supervisor, SupervisorReport, supervise};
+
 use rand::Rng;
 use rand::SeedableRng;
 use rand::rngs::SmallRng;
+use shutdown::ShuttingDown;
+use std::convert::Infallible;
+use std::panic::Location;
 use std::time::Duration;
 
 // Re-export the proc-macro so users can just write `#[retry]` or `#[retry(3)]` when depending on this crate
 pub use asyn_retry_policy_macro::retry;
 
+// Support code for the generated code of `#[retry]`; not a public API
+#[doc(hidden)]
+pub mod __private {
+    pub use crate::reborrow::Reborrow;
+    pub use crate::retryable::{DefaultPredicate, ViaAlways, ViaRetryable, retry_or_else_with};
+    pub use crate::shutdown::retry_until_shutdown_with;
+}
+
 /// Retry policy configuration
 #[derive(Clone, Debug)]
 pub struct RetryPolicy {
@@ -84,35 +137,109 @@ pub struct RetryPolicy {
     pub backoff_factor: f64,
     /// Use random jitter between 0..delay
     pub jitter: bool,
+    /// Optional time budget for the whole sequence; no backoff sleeps past it
+    pub max_elapsed: Option<Duration>,
+    /// What a sequence of this policy does when it runs inside another retry sequence
+    /// (`NestedRetries::Allow` by default)
+    pub on_nested: NestedRetries,
     /// Optional RNG seed to allow deterministic jitter for testing
     pub rng_seed: Option<u64>,
+    /// Optional name identifying the policy in dead letters and diagnostics
+    pub name: Option<&'static str>,
+    /// Coordinator whose shutdown drains or interrupts sequences run with this policy
+    pub shutdown: Option<ShutdownCoordinator>,
 }
 
 impl Default for RetryPolicy {
     fn default() -> Self {
-        Self {
-            attempts: 3,
-            base_delay: Duration::from_millis(100),
-            max_delay: Duration::from_secs(5),
-            backoff_factor: 2.0,
-            jitter: true,
-            rng_seed: None,
-        }
+        Self::DEFAULT
     }
 }
 
+/// Statistics about a finished retry sequence.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct RetryStats {
+    /// Number of attempts that were made (including the first try)
+    pub attempts: usize,
+    /// Total time spent sleeping between attempts
+    pub total_delay: Duration,
+    /// Wall-clock time from the first attempt until the sequence finished
+    pub elapsed: Duration,
+}
+
 impl RetryPolicy {
+    /// The default policy, usable in `const` and `static` items:
+    ///
+    /// ```
+    /// use asyn_retry_policy::RetryPolicy;
+    /// use std::time::Duration;
+    ///
+    /// const DB_POLICY: RetryPolicy = RetryPolicy {
+    ///     attempts: 5,
+    ///     base_delay: Duration::from_millis(50),
+    ///     ..RetryPolicy::DEFAULT
+    /// };
+    /// ```
+    pub const DEFAULT: RetryPolicy = RetryPolicy {
+        attempts: 3,
+        base_delay: Duration::from_millis(100),
+        max_delay: Duration::from_secs(5),
+        backoff_factor: 2.0,
+        jitter: true,
+        max_elapsed: None,
+        on_nested: NestedRetries::Allow,
+        rng_seed: None,
+        name: None,
+        shutdown: None,
+    };
+
     /// Compute the exponential backoff (without jitter) clamped by `max_delay`.
+    ///
+    /// Saturates at `max_delay` however large `attempt` gets, so policies with
+    /// `attempts = usize::MAX` keep backing off instead of overflowing.
     pub fn compute_backoff(&self, attempt: usize) -> Duration {
-        let exp = self.backoff_factor.powi((attempt - 1) as i32);
-        self.base_delay.mul_f64(exp).min(self.max_delay)
+        if self.base_delay.is_zero() {
+            return Duration::ZERO;
+        }
+        let exp = i32::try_from(attempt.saturating_sub(1)).unwrap_or(i32::MAX);
+        let factor = self.backoff_factor.powi(exp);
+        Duration::try_from_secs_f64(self.base_delay.as_secs_f64() * factor)
+            .map_or(self.max_delay, |delay| delay.min(self.max_delay))
+    }
+
+    /// Compute the delay to sleep after `attempt` failed, applying jitter if enabled.
+    ///
+    /// With `rng_seed` set the jitter is deterministic per attempt.
+    pub fn jittered_delay(&self, attempt: usize) -> Duration {
+        let delay = self.compute_backoff(attempt);
+        if !self.jitter {
+            return delay;
+        }
+        let max_ms = delay.as_millis().max(1) as u64;
+        let jitter_ms = if let Some(seed) = self.rng_seed {
+            // deterministic per-attempt RNG to keep testability
+            let mut rng = SmallRng::seed_from_u64(seed.wrapping_add(attempt as u64));
+            rng.gen_range(0..=max_ms)
+        } else {
+            rand::thread_rng().gen_range(0..=max_ms)
+        };
+        Duration::from_millis(jitter_ms)
     }
 
     /// Retry an asynchronous operation described by `f` with this policy.
     ///
     /// `f` must return a `Result<T, E>`. The `should_retry` predicate receives a reference to the error
     /// and returns whether the operation should be retried.
-    pub async fn retry<Fut, T, E, F, P>(&self, mut f: F, mut should_retry: P) -> Result<T, E>
+    ///
+    /// A sequence interrupted by the policy's [`ShutdownCoordinator`] also returns its last error,
+    /// indistinguishable from a final failure; use [`RetryPolicy::retry_until_shutdown`] (or
+    /// `#[retry(until_shutdown)]`) to get [`RetryError::ShuttingDown`] instead.
+    #[track_caller]
+    pub fn retry<Fut, T, E, F, P>(
+        &self,
+        f: F,
+        should_retry: P,
+    ) -> impl std::future::Future<Output = Result<T, E>>
     where
         F: FnMut() -> Fut,
         Fut: std::future::Future<Output = Result<T, E>> + Send,
@@ -120,36 +247,258 @@ impl RetryPolicy {
         E: Send,
         P: FnMut(&E) -> bool,
     {
-        for attempt in 1..=self.attempts {
-            match f().await {
-                Ok(v) => return Ok(v),
-                Err(e) if attempt < self.attempts && should_retry(&e) => {
-                    // Calculate exponential backoff
-                    let mut delay = self.compute_backoff(attempt);
-
-                    // Apply jitter
-                    if self.jitter {
-                        let max_ms = delay.as_millis().max(1) as u64;
-                        let jitter_ms = if let Some(seed) = self.rng_seed {
-                            // deterministic per-attempt RNG to keep testability
-                            let mut rng = SmallRng::seed_from_u64(seed.wrapping_add(attempt as u64));
-                            rng.gen_range(0..=max_ms)
-                        } else {
-                            rand::thread_rng().gen_range(0..=max_ms)
-                        };
-                        delay = Duration::from_millis(jitter_ms);
-                    }
+        let site = Location::caller();
+        async move {
+            self.run(f, should_retry, site)
+                .await
+                .map(|(v, _)| v)
+                .map_err(|(e, _)| e)
+        }
+    }
+
+    /// Like [`RetryPolicy::retry`], but takes any [`RetryPredicate`], such as a composed
+    /// predicate or [`IsRetryable`]; delays from [`RetryPredicate::retry_after`] replace the
+    /// backoff.
+    #[track_caller]
+    pub fn retry_with<Fut, T, E, F, P>(
+        &self,
+        f: F,
+        should_retry: P,
+    ) -> impl std::future::Future<Output = Result<T, E>>
+    where
+        F: FnMut() -> Fut,
+        Fut: std::future::Future<Output = Result<T, E>>,
+        P: RetryPredicate<E>,
+    {
+        let site = Location::caller();
+        async move {
+            self.run(f, should_retry, site)
+                .await
+                .map(|(v, _)| v)
+                .map_err(|(e, _)| e)
+        }
+    }
+
+    /// Like [`RetryPolicy::retry`], but calls `fallback` once the retries are exhausted
+    /// (or the predicate rejects the error).
+    ///
+    /// The fallback receives the final error together with the [`RetryStats`] of the
+    /// sequence and produces the result returned to the caller, e.g. a stale cached
+    /// value or a default.
+    #[track_caller]
+    pub fn retry_or_else<Fut, T, E, F, P, FB, FbFut>(
+        &self,
+        f: F,
+        should_retry: P,
+        fallback: FB,
+    ) -> impl std::future::Future<Output = Result<T, E>>
+    where
+        F: FnMut() -> Fut,
+        Fut: std::future::Future<Output = Result<T, E>> + Send,
+        T: Send,
+        E: Send,
+        P: FnMut(&E) -> bool,
+        FB: FnOnce(E, RetryStats) -> FbFut,
+        FbFut: std::future::Future<Output = Result<T, E>>,
+    {
+        let site = Location::caller();
+        async move {
+            match self.run(f, should_retry, site).await {
+                Ok((v, _)) => Ok(v),
+                Err((e, stats)) => fallback(e, stats).await,
+            }
+        }
+    }
 
-                    tokio::time::sleep(delay).await;
-                    continue;
+    /// The retry loop shared by the public entry points; reports stats on both paths.
+    ///
+    /// `site` is where the caller started the sequence, used to report nested retries.
+    async fn run<Fut, T, E, F, P>(
+        &self,
+        f: F,
+        should_retry: P,
+        site: &'static Location<'static>,
+    ) -> Result<(T, RetryStats), (E, RetryStats)>
+    where
+        F: FnMut() -> Fut,
+        Fut: std::future::Future<Output = Result<T, E>>,
+        P: RetryPredicate<E>,
+    {
+        uninterrupted(self.run_hooked(f, should_retry, site, &mut Sleep).await)
+    }
+
+    /// [`RetryPolicy::run`] with hooks observing attempts and replacing the backoff sleep.
+    ///
+    /// Hooks may interrupt the sequence while an attempt runs or while it backs off; the
+    /// interruption is reported together with the last error seen.
+    pub(crate) async fn run_hooked<Fut, T, E, F, P, H>(
+        &self,
+        mut f: F,
+        mut should_retry: P,
+        site: &'static Location<'static>,
+        hooks: &mut H,
+    ) -> Result<(T, RetryStats), (Halt<E, H::Interrupt>, RetryStats)>
+    where
+        F: FnMut() -> Fut,
+        Fut: std::future::Future<Output = Result<T, E>>,
+        P: RetryPredicate<E>,
+        H: RetryHooks,
+    {
+        let start = tokio::time::Instant::now();
+        let mut stats = RetryStats::default();
+        let mut last_error = None;
+        let (context, attempts) = RetryContext::enter(self, start, site);
+        let mut registration = self.shutdown.as_ref().map(ShutdownCoordinator::enter);
+        loop {
+            stats.attempts += 1;
+            let attempt = stats.attempts;
+            hooks.before_attempt(attempt);
+            let result = match hooks.attempt(context.scope(&mut f)).await {
+                Ok(result) => result,
+                Err(interrupt) => {
+                    // the interrupted attempt did not complete
+                    stats.attempts -= 1;
+                    stats.elapsed = start.elapsed();
+                    return Err((Halt::Interrupted(interrupt, last_error), stats));
+                }
+            };
+            match result {
+                Ok(v) => {
+                    stats.elapsed = start.elapsed();
+                    return Ok((v, stats));
+                }
+                Err(e) if attempt < attempts && should_retry.should_retry(&e) => {
+                    let delay = should_retry
+                        .retry_after(&e)
+                        .unwrap_or_else(|| self.jittered_delay(attempt));
+                    if context.remaining().is_some_and(|left| left <= delay) {
+                        // the next attempt would start past the deadline
+                        stats.elapsed = start.elapsed();
+                        return Err((Halt::Failed(e), stats));
+                    }
+                    let sleep_start = tokio::time::Instant::now();
+                    let waited = match &mut registration {
+                        Some(registration) => registration.wait(hooks.wait(delay)).await,
+                        None => Ok(Some(hooks.wait(delay).await)),
+                    };
+                    stats.total_delay += sleep_start.elapsed();
+                    match waited {
+                        Ok(Some(Ok(()))) | Ok(None) => last_error = Some(e),
+                        Ok(Some(Err(interrupt))) => {
+                            stats.elapsed = start.elapsed();
+                            return Err((Halt::Interrupted(interrupt, Some(e)), stats));
+                        }
+                        Err(ShuttingDown) => {
+                            stats.elapsed = start.elapsed();
+                            return Err((Halt::ShuttingDown(e), stats));
+                        }
+                    }
+                }
+                Err(e) => {
+                    stats.elapsed = start.elapsed();
+                    return Err((Halt::Failed(e), stats));
                 }
-                Err(e) => return Err(e),
             }
         }
-        unreachable!("loop returns or errors")
     }
 }
 
+/// Error returned by retry variants that can stop before the policy is exhausted.
+#[derive(Debug, thiserror::Error)]
+pub enum RetryError<E> {
+    /// The last attempt failed and was not retried (policy exhausted or predicate rejected)
+    #[error("{0}")]
+    Failed(E),
+    /// The sequence was cancelled before it finished
+    #[error("retry cancelled after {} attempt(s)", .stats.attempts)]
+    Cancelled {
+        /// Error of the last completed attempt, if any
+        last_error: Option<E>,
+        /// Stats of the sequence up to the cancellation
+        stats: RetryStats,
+    },
+    /// The policy's [`ShutdownCoordinator`] stopped the sequence instead of retrying
+    #[error("retry interrupted by shutdown after {} attempt(s): {last_error}", .stats.attempts)]
+    ShuttingDown {
+        /// Error of the last attempt
+        last_error: E,
+        /// Stats of the sequence up to the shutdown
+        stats: RetryStats,
+    },
+}
+
+impl<E> RetryError<E> {
+    /// The error of the last completed attempt, if any.
+    pub fn last_error(&self) -> Option<&E> {
+        match self {
+            RetryError::Failed(e) => Some(e),
+            RetryError::Cancelled { last_error, .. } => last_error.as_ref(),
+            RetryError::ShuttingDown { last_error, .. } => Some(last_error),
+        }
+    }
+
+    /// Consume the error, returning the error of the last completed attempt, if any.
+    pub fn into_last_error(self) -> Option<E> {
+        match self {
+            RetryError::Failed(e) => Some(e),
+            RetryError::Cancelled { last_error, .. } => last_error,
+            RetryError::ShuttingDown { last_error, .. } => Some(last_error),
+        }
+    }
+}
+
+/// How a hooked retry sequence ended without success.
+pub(crate) enum Halt<E, I> {
+    /// The last attempt failed and was not retried
+    Failed(E),
+    /// A hook stopped the sequence; carries the last error seen
+    Interrupted(I, Option<E>),
+    /// The policy's shutdown coordinator stopped the sequence after this error
+    ShuttingDown(E),
+}
+
+/// Unwrap the result of a sequence whose hooks can never interrupt it.
+///
+/// A sequence stopped by a shutdown fails with its last error.
+pub(crate) fn uninterrupted<T, E>(
+    result: Result<(T, RetryStats), (Halt<E, Infallible>, RetryStats)>,
+) -> Result<(T, RetryStats), (E, RetryStats)> {
+    result.map_err(|(halt, stats)| match halt {
+        Halt::Failed(e) | Halt::ShuttingDown(e) => (e, stats),
+        Halt::Interrupted(never, _) => match never {},
+    })
+}
+
+/// Extension points of the retry loop used by the wrappers in this crate.
+pub(crate) trait RetryHooks {
+    /// Reason a hook stops the sequence early; `Infallible` for hooks that never do.
+    type Interrupt;
+
+    /// Called before every attempt with its 1-based number.
+    fn before_attempt(&mut self, _attempt: usize) {}
+
+    /// Drive a single attempt to completion.
+    async fn attempt<Fut: std::future::Future>(
+        &mut self,
+        attempt: Fut,
+    ) -> Result<Fut::Output, Self::Interrupt> {
+        Ok(attempt.await)
+    }
+
+    /// Wait between attempts; `delay` is the backoff computed by the policy.
+    async fn wait(&mut self, delay: Duration) -> Result<(), Self::Interrupt> {
+        tokio::time::sleep(delay).await;
+        Ok(())
+    }
+}
+
+/// Plain backoff sleep without any extra behavior.
+pub(crate) struct Sleep;
+
+impl RetryHooks for Sleep {
+    type Interrupt = Infallible;
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
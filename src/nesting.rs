@@ -0,0 +1,102 @@
+//! Detection of retry amplification through nested retry layers.
+//!
+//! A retried operation that itself retries multiplies the attempts: 3 outer × 3 inner
+//! attempts make up to 9 calls to the backend. The task-local [`RetryContext`] marks running
+//! sequences, so a nested sequence knows it is nested and applies its policy's
+//! [`NestedRetries`] behavior.
+
+use crate::context::RetryContext;
+use std::collections::HashSet;
+use std::fmt;
+use std::panic::Location;
+use std::sync::{Mutex, RwLock};
+
+/// What a retry sequence does when it starts inside an attempt of another one.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum NestedRetries {
+    /// Retry as configured
+    #[default]
+    Allow,
+    /// Retry as configured, but report the nesting once per pair of call sites to the handler
+    /// set with [`set_nested_retry_handler`]
+    Warn,
+    /// Make a single attempt; the outer layer does the retrying
+    Disable,
+    /// Limit the attempts so that the product of the attempts of all layers stays within
+    /// the given total (the nested sequence still makes at least one attempt)
+    CapTotal(usize),
+}
+
+/// A retry sequence found running inside another one.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct NestedRetry {
+    /// Where the enclosing sequence was started
+    pub outer: &'static Location<'static>,
+    /// Where the nested sequence was started
+    pub inner: &'static Location<'static>,
+    /// Nesting depth of the inner sequence (2 when directly inside the outermost one)
+    pub depth: usize,
+    /// Attempts the operation can see across all layers
+    pub total_attempts: usize,
+}
+
+impl fmt::Display for NestedRetry {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "retry at {} runs inside the retry at {} (depth {}): up to {} attempts in total",
+            self.inner, self.outer, self.depth, self.total_attempts
+        )
+    }
+}
+
+type Handler = fn(&NestedRetry);
+
+static HANDLER: RwLock<Handler> = RwLock::new(|_| {});
+
+/// Set how [`NestedRetries::Warn`] reports nested retries.
+///
+/// Nothing is reported until a handler is set, so a library using `Warn` stays silent unless
+/// the application forwards the reports to its own logging, e.g.
+/// `set_nested_retry_handler(|nested| log::warn!("{nested}"))`.
+pub fn set_nested_retry_handler(handler: fn(&NestedRetry)) {
+    *HANDLER.write().unwrap() = handler;
+}
+
+/// Report `nested` unless its pair of call sites was already reported.
+fn warn_once(nested: &NestedRetry) {
+    static SEEN: Mutex<Option<HashSet<(Location<'static>, Location<'static>)>>> = Mutex::new(None);
+    let first = SEEN
+        .lock()
+        .unwrap()
+        .get_or_insert_with(HashSet::new)
+        .insert((*nested.outer, *nested.inner));
+    if first {
+        (HANDLER.read().unwrap())(nested);
+    }
+}
+
+impl NestedRetries {
+    /// The attempts a sequence started at `site` with `attempts` may make inside `outer`.
+    pub(crate) fn attempts(
+        self,
+        outer: &RetryContext,
+        site: &'static Location<'static>,
+        attempts: usize,
+    ) -> usize {
+        match self {
+            NestedRetries::Allow => attempts,
+            NestedRetries::Warn => {
+                warn_once(&NestedRetry {
+                    outer: outer.site(),
+                    inner: site,
+                    depth: outer.depth() + 1,
+                    total_attempts: outer.total_attempts().saturating_mul(attempts),
+                });
+                attempts
+            }
+            NestedRetries::Disable => 1,
+            NestedRetries::CapTotal(total) => attempts.min(total / outer.total_attempts()).max(1),
+        }
+    }
+}
@@ -0,0 +1,99 @@
+//! Treat panicking attempts as failures instead of unwinding the task.
+//!
+//! [`RetryPolicy::retry_catch_unwind`] wraps every attempt in `catch_unwind`, so a panic in
+//! the operation becomes an [`AttemptError::Panicked`] carrying the panic message. The
+//! predicate decides whether panics are retried like any other failure.
+//!
+//! The panic hook still runs for every caught panic, so panics are printed as usual unless
+//! the hook is replaced.
+
+use crate::RetryPolicy;
+use futures::FutureExt;
+use std::any::Any;
+use std::future::Future;
+use std::panic::{AssertUnwindSafe, Location};
+
+/// Failure of an attempt run by [`RetryPolicy::retry_catch_unwind`].
+#[derive(Debug, PartialEq, Eq, thiserror::Error)]
+pub enum AttemptError<E> {
+    /// The operation returned an error
+    #[error("{0}")]
+    Failed(E),
+    /// The operation panicked
+    #[error("attempt panicked: {message}")]
+    Panicked {
+        /// The panic message, if the payload was a string
+        message: String,
+    },
+}
+
+impl<E> AttemptError<E> {
+    /// Returns true if the attempt panicked.
+    pub fn is_panic(&self) -> bool {
+        matches!(self, AttemptError::Panicked { .. })
+    }
+
+    /// The operation's error, unless the attempt panicked.
+    pub fn into_failed(self) -> Option<E> {
+        match self {
+            AttemptError::Failed(e) => Some(e),
+            AttemptError::Panicked { .. } => None,
+        }
+    }
+}
+
+fn panic_message(payload: Box<dyn Any + Send>) -> String {
+    match payload.downcast::<String>() {
+        Ok(message) => *message,
+        Err(payload) => match payload.downcast::<&'static str>() {
+            Ok(message) => message.to_string(),
+            Err(_) => "Box<dyn Any>".to_string(),
+        },
+    }
+}
+
+impl RetryPolicy {
+    /// Like [`RetryPolicy::retry`], but a panic inside an attempt (while `f` creates the future
+    /// or while it runs) becomes an [`AttemptError::Panicked`] failure.
+    ///
+    /// `should_retry` sees both kinds of failure, e.g. `|e| e.is_panic()` retries panics only.
+    /// The operation's state is not inspected for consistency after a panic, as with
+    /// [`AssertUnwindSafe`].
+    #[track_caller]
+    pub fn retry_catch_unwind<Fut, T, E, F, P>(
+        &self,
+        mut f: F,
+        should_retry: P,
+    ) -> impl Future<Output = Result<T, AttemptError<E>>>
+    where
+        F: FnMut() -> Fut,
+        Fut: Future<Output = Result<T, E>>,
+        P: FnMut(&AttemptError<E>) -> bool,
+    {
+        let site = Location::caller();
+        async move {
+            self.run(
+                || {
+                    let attempt = std::panic::catch_unwind(AssertUnwindSafe(&mut f));
+                    async move {
+                        let caught = match attempt {
+                            Ok(attempt) => AssertUnwindSafe(attempt).catch_unwind().await,
+                            Err(payload) => Err(payload),
+                        };
+                        match caught {
+                            Ok(result) => result.map_err(AttemptError::Failed),
+                            Err(payload) => Err(AttemptError::Panicked {
+                                message: panic_message(payload),
+                            }),
+                        }
+                    }
+                },
+                should_retry,
+                site,
+            )
+            .await
+            .map(|(v, _)| v)
+            .map_err(|(e, _)| e)
+        }
+    }
+}
@@ -0,0 +1,104 @@
+//! Retry successful-but-unsatisfactory results.
+//!
+//! [`poll_until`] keeps polling an operation until its value satisfies a predicate (a job
+//! reaching `Done`, an eventually consistent read showing the write), and
+//! [`RetryPolicy::retry_if_result`] decides on the whole `Result` of every attempt. Both run the
+//! regular retry loop, so backoff, `max_elapsed`, nesting and shutdown apply as for
+//! [`RetryPolicy::retry`].
+
+use crate::RetryPolicy;
+use crate::predicate::Always;
+use futures::FutureExt;
+use std::future::Future;
+use std::panic::Location;
+use std::sync::Mutex;
+
+/// Failure of [`poll_until`].
+#[derive(Debug, PartialEq, Eq, thiserror::Error)]
+pub enum PollError<T, E> {
+    /// The value was still not ready when the policy was exhausted; carries the last value
+    #[error("value not ready after retries")]
+    NotReady(T),
+    /// The operation returned an error, which is not retried
+    #[error("{0}")]
+    Failed(E),
+}
+
+/// Poll `op` with `policy` until the value it returns satisfies `ready`.
+///
+/// Values rejected by `ready` are retried with the policy's backoff; errors end the polling
+/// immediately. Use [`RetryPolicy::retry_if_result`] to retry errors as well.
+#[track_caller]
+pub fn poll_until<Fut, T, E, F, R>(
+    policy: &RetryPolicy,
+    mut op: F,
+    ready: R,
+) -> impl Future<Output = Result<T, PollError<T, E>>>
+where
+    F: FnMut() -> Fut,
+    Fut: Future<Output = Result<T, E>>,
+    R: FnMut(&T) -> bool,
+{
+    let site = Location::caller();
+    async move {
+        let ready = Mutex::new(ready);
+        policy
+            .run(
+                || {
+                    let ready = &ready;
+                    op().map(move |res| match res {
+                        Ok(v) if (ready.lock().unwrap())(&v) => Ok(v),
+                        Ok(v) => Err(PollError::NotReady(v)),
+                        Err(e) => Err(PollError::Failed(e)),
+                    })
+                },
+                |e: &_| matches!(e, PollError::NotReady(_)),
+                site,
+            )
+            .await
+            .map(|(v, _)| v)
+            .map_err(|(e, _)| e)
+    }
+}
+
+impl RetryPolicy {
+    /// Retry `f` while `should_retry` returns true for the `Result` of the attempt.
+    ///
+    /// Unlike [`RetryPolicy::retry`], `Ok` values can be retried too. Returns the result of the
+    /// last attempt, whether it was accepted or the policy was exhausted.
+    #[track_caller]
+    pub fn retry_if_result<Fut, T, E, F, D>(
+        &self,
+        mut f: F,
+        should_retry: D,
+    ) -> impl Future<Output = Result<T, E>>
+    where
+        F: FnMut() -> Fut,
+        Fut: Future<Output = Result<T, E>>,
+        D: FnMut(&Result<T, E>) -> bool,
+    {
+        let site = Location::caller();
+        async move {
+            let should_retry = Mutex::new(should_retry);
+            let outcome = self
+                .run(
+                    || {
+                        let should_retry = &should_retry;
+                        f().map(move |res| {
+                            if (should_retry.lock().unwrap())(&res) {
+                                Err(res)
+                            } else {
+                                Ok(res)
+                            }
+                        })
+                    },
+                    Always,
+                    site,
+                )
+                .await;
+            match outcome {
+                Ok((res, _)) | Err((res, _)) => res,
+            }
+        }
+    }
+}
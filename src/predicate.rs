@@ -0,0 +1,279 @@
+//! Composable retry predicates.
+//!
+//! [`RetryPredicate`] is implemented by every `FnMut(&E) -> bool` and by the ready-made
+//! predicates in this module, and combines them with [`and`](RetryPredicate::and),
+//! [`or`](RetryPredicate::or), [`not`](RetryPredicate::not) and
+//! [`max_times`](RetryPredicate::max_times). Call [`into_fn`](RetryPredicate::into_fn) to pass
+//! a composed predicate to [`RetryPolicy::retry`](crate::RetryPolicy::retry), or pass it to
+//! [`RetryPolicy::retry_with`](crate::RetryPolicy::retry_with) as it is; `#[retry]` accepts a
+//! predicate expression such as `predicate = transient()` directly and keeps its
+//! [`retry_after`](RetryPredicate::retry_after) delays.
+//!
+//! ```
+//! use asyn_retry_policy::predicate::{RetryPredicate, when};
+//! use asyn_retry_policy::retry_matches;
+//!
+//! #[derive(Debug)]
+//! enum DbError { Timeout, Deadlock, Constraint }
+//!
+//! // retry timeouts freely, deadlocks at most twice
+//! let mut transient = retry_matches!(DbError::Timeout)
+//!     .or(when(|e: &DbError| matches!(e, DbError::Deadlock)).max_times(2))
+//!     .into_fn();
+//! assert!(transient(&DbError::Timeout));
+//! assert!(transient(&DbError::Deadlock));
+//! assert!(transient(&DbError::Deadlock));
+//! assert!(!transient(&DbError::Deadlock));
+//! assert!(!transient(&DbError::Constraint));
+//! ```
+
+use crate::{IsRetryable, Retryable};
+use std::error::Error;
+use std::marker::PhantomData;
+use std::time::Duration;
+
+/// Decides whether an error is retried.
+pub trait RetryPredicate<E: ?Sized> {
+    /// Returns true if `error` should be retried.
+    fn should_retry(&mut self, error: &E) -> bool;
+
+    /// Delay before retrying `error`, replacing the policy's backoff; `None` keeps the backoff.
+    fn retry_after(&mut self, _error: &E) -> Option<Duration> {
+        None
+    }
+
+    /// Retry only errors accepted by both predicates.
+    fn and<Q: RetryPredicate<E>>(self, other: Q) -> And<Self, Q>
+    where
+        Self: Sized,
+    {
+        And(self, other)
+    }
+
+    /// Retry errors accepted by either predicate.
+    fn or<Q: RetryPredicate<E>>(self, other: Q) -> Or<Self, Q>
+    where
+        Self: Sized,
+    {
+        Or(self, other)
+    }
+
+    /// Retry exactly the errors this predicate rejects.
+    fn not(self) -> Not<Self>
+    where
+        Self: Sized,
+    {
+        Not(self)
+    }
+
+    /// Accept at most `n` errors; later errors matching this predicate are not retried.
+    ///
+    /// The count is kept in the predicate, so build (or clone) one per retry sequence.
+    fn max_times(self, n: usize) -> MaxTimes<Self>
+    where
+        Self: Sized,
+    {
+        MaxTimes {
+            inner: self,
+            remaining: n,
+        }
+    }
+
+    /// Turn the predicate into a closure accepted by the `RetryPolicy` methods.
+    ///
+    /// The closure only decides whether to retry: pass the predicate itself to
+    /// [`RetryPolicy::retry_with`](crate::RetryPolicy::retry_with) to keep its
+    /// [`retry_after`](RetryPredicate::retry_after) delays.
+    fn into_fn(mut self) -> impl FnMut(&E) -> bool
+    where
+        Self: Sized,
+    {
+        move |e| self.should_retry(e)
+    }
+}
+
+impl<E: ?Sized, F: FnMut(&E) -> bool> RetryPredicate<E> for F {
+    fn should_retry(&mut self, error: &E) -> bool {
+        self(error)
+    }
+}
+
+/// See [`RetryPredicate::and`].
+#[derive(Clone, Copy, Debug)]
+pub struct And<A, B>(A, B);
+
+impl<E: ?Sized, A: RetryPredicate<E>, B: RetryPredicate<E>> RetryPredicate<E> for And<A, B> {
+    fn should_retry(&mut self, error: &E) -> bool {
+        self.0.should_retry(error) && self.1.should_retry(error)
+    }
+
+    fn retry_after(&mut self, error: &E) -> Option<Duration> {
+        self.0
+            .retry_after(error)
+            .or_else(|| self.1.retry_after(error))
+    }
+}
+
+/// See [`RetryPredicate::or`].
+#[derive(Clone, Copy, Debug)]
+pub struct Or<A, B>(A, B);
+
+impl<E: ?Sized, A: RetryPredicate<E>, B: RetryPredicate<E>> RetryPredicate<E> for Or<A, B> {
+    fn should_retry(&mut self, error: &E) -> bool {
+        self.0.should_retry(error) || self.1.should_retry(error)
+    }
+
+    fn retry_after(&mut self, error: &E) -> Option<Duration> {
+        self.0
+            .retry_after(error)
+            .or_else(|| self.1.retry_after(error))
+    }
+}
+
+/// See [`RetryPredicate::not`].
+#[derive(Clone, Copy, Debug)]
+pub struct Not<A>(A);
+
+impl<E: ?Sized, A: RetryPredicate<E>> RetryPredicate<E> for Not<A> {
+    fn should_retry(&mut self, error: &E) -> bool {
+        !self.0.should_retry(error)
+    }
+}
+
+/// See [`RetryPredicate::max_times`].
+#[derive(Clone, Copy, Debug)]
+pub struct MaxTimes<A> {
+    inner: A,
+    remaining: usize,
+}
+
+impl<E: ?Sized, A: RetryPredicate<E>> RetryPredicate<E> for MaxTimes<A> {
+    fn should_retry(&mut self, error: &E) -> bool {
+        if self.remaining == 0 || !self.inner.should_retry(error) {
+            return false;
+        }
+        self.remaining -= 1;
+        true
+    }
+
+    fn retry_after(&mut self, error: &E) -> Option<Duration> {
+        self.inner.retry_after(error)
+    }
+}
+
+/// Predicate retrying every error.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct Always;
+
+impl<E: ?Sized> RetryPredicate<E> for Always {
+    fn should_retry(&mut self, _error: &E) -> bool {
+        true
+    }
+}
+
+/// Predicate retrying no error.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct Never;
+
+impl<E: ?Sized> RetryPredicate<E> for Never {
+    fn should_retry(&mut self, _error: &E) -> bool {
+        false
+    }
+}
+
+/// Retry every error.
+pub fn always() -> Always {
+    Always
+}
+
+/// Retry no error.
+pub fn never() -> Never {
+    Never
+}
+
+/// [`IsRetryable`] for errors of type `E`, e.g. to start a chain of combinators.
+///
+/// `IsRetryable.max_times(3)` does not compile because `IsRetryable` is a predicate for every
+/// [`Retryable`] error; `is_retryable::<E>().max_times(3)` names the one to use.
+pub fn is_retryable<E: Retryable + ?Sized>() -> impl RetryPredicate<E> + Copy + std::fmt::Debug {
+    IsRetryable
+}
+
+/// Use a closure as a predicate, e.g. to start a chain of combinators.
+pub fn when<E: ?Sized, F: FnMut(&E) -> bool>(f: F) -> F {
+    f
+}
+
+/// Predicate retrying type-erased errors that are, or are caused by, a `T`.
+///
+/// Implemented for `Box<dyn Error>` (with and without `Send + Sync`), `dyn Error` and, with
+/// the `anyhow` feature, `anyhow::Error`; the whole `source()` chain is searched.
+pub struct OnErrorType<T>(PhantomData<fn() -> T>);
+
+impl<T> Clone for OnErrorType<T> {
+    fn clone(&self) -> Self {
+        *self
+    }
+}
+
+impl<T> Copy for OnErrorType<T> {}
+
+impl<T> std::fmt::Debug for OnErrorType<T> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "OnErrorType<{}>", std::any::type_name::<T>())
+    }
+}
+
+/// Retry errors whose `source()` chain contains a `T`.
+pub fn on_error_type<T: Error + 'static>() -> OnErrorType<T> {
+    OnErrorType(PhantomData)
+}
+
+fn chain_contains<T: Error + 'static>(error: &(dyn Error + 'static)) -> bool {
+    std::iter::successors(Some(error), |&e| e.source()).any(|e| e.is::<T>())
+}
+
+impl<T: Error + 'static> RetryPredicate<dyn Error> for OnErrorType<T> {
+    fn should_retry(&mut self, error: &(dyn Error + 'static)) -> bool {
+        chain_contains::<T>(error)
+    }
+}
+
+impl<T: Error + 'static> RetryPredicate<Box<dyn Error>> for OnErrorType<T> {
+    fn should_retry(&mut self, error: &Box<dyn Error>) -> bool {
+        chain_contains::<T>(error.as_ref())
+    }
+}
+
+impl<T: Error + 'static> RetryPredicate<Box<dyn Error + Send + Sync>> for OnErrorType<T> {
+    fn should_retry(&mut self, error: &Box<dyn Error + Send + Sync>) -> bool {
+        chain_contains::<T>(error.as_ref())
+    }
+}
+
+#[cfg(feature = "anyhow")]
+impl<T: Error + Send + Sync + 'static> RetryPredicate<anyhow::Error> for OnErrorType<T> {
+    fn should_retry(&mut self, error: &anyhow::Error) -> bool {
+        error.chain().any(|e| e.is::<T>())
+    }
+}
+
+/// Build a predicate retrying errors that match a pattern, like `matches!`.
+///
+/// ```
+/// use asyn_retry_policy::retry_matches;
+/// use asyn_retry_policy::predicate::RetryPredicate;
+///
+/// enum HttpError { Status(u16), Io }
+/// let mut retryable = retry_matches!(HttpError::Status(500..=599) | HttpError::Io).into_fn();
+/// assert!(retryable(&HttpError::Status(503)));
+/// assert!(!retryable(&HttpError::Status(404)));
+/// ```
+#[macro_export]
+macro_rules! retry_matches {
+    ($($pattern:pat_param)|+ $(if $guard:expr)?) => {
+        $crate::predicate::when(|error: &_| {
+            ::std::matches!(error, $($pattern)|+ $(if $guard)?)
+        })
+    };
+}
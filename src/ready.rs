@@ -0,0 +1,122 @@
+//! Gate retries on an external readiness signal instead of blind sleeping.
+//!
+//! When the reason for failing is known (network down, leader election, token refresh),
+//! [`RetryPolicy::retry_when_ready`] retries as soon as a user-supplied signal fires, with the
+//! computed backoff acting as an upper bound on the wait.
+
+use crate::{RetryHooks, RetryPolicy, uninterrupted};
+use std::convert::Infallible;
+use std::future::Future;
+use std::panic::Location;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::{Notify, watch};
+
+/// A signal that resolves when retrying is likely to succeed.
+pub trait ReadySignal {
+    /// Resolve once the signal fires.
+    fn ready(&mut self) -> impl Future<Output = ()> + Send + '_;
+}
+
+impl ReadySignal for &Notify {
+    fn ready(&mut self) -> impl Future<Output = ()> + Send + '_ {
+        self.notified()
+    }
+}
+
+impl ReadySignal for Arc<Notify> {
+    fn ready(&mut self) -> impl Future<Output = ()> + Send + '_ {
+        self.notified()
+    }
+}
+
+/// Fires when the value changes to `true`; a value that is already `true` when the wait
+/// starts is not a new signal, so the backoff applies.
+impl ReadySignal for watch::Receiver<bool> {
+    async fn ready(&mut self) {
+        self.borrow_and_update();
+        loop {
+            if self.changed().await.is_err() {
+                // sender dropped: no signal will ever come, fall back to the backoff
+                return std::future::pending().await;
+            }
+            if *self.borrow_and_update() {
+                return;
+            }
+        }
+    }
+}
+
+/// A readiness signal together with how it combines with the backoff.
+#[derive(Debug)]
+pub struct ReadyGate<S> {
+    signal: S,
+    min_delay: Duration,
+}
+
+impl<S: ReadySignal> ReadyGate<S> {
+    /// Retry when `signal` fires or the backoff elapses, whichever comes first.
+    pub fn new(signal: S) -> Self {
+        Self {
+            signal,
+            min_delay: Duration::ZERO,
+        }
+    }
+
+    /// Always wait at least `min_delay` (capped by the backoff) before reacting to the signal.
+    pub fn min_delay(mut self, min_delay: Duration) -> Self {
+        self.min_delay = min_delay;
+        self
+    }
+}
+
+impl<S: ReadySignal> From<S> for ReadyGate<S> {
+    fn from(signal: S) -> Self {
+        Self::new(signal)
+    }
+}
+
+impl<S: ReadySignal> RetryHooks for ReadyGate<S> {
+    type Interrupt = Infallible;
+
+    async fn wait(&mut self, delay: Duration) -> Result<(), Infallible> {
+        let min_delay = self.min_delay.min(delay);
+        tokio::time::sleep(min_delay).await;
+        tokio::select! {
+            _ = tokio::time::sleep(delay - min_delay) => {}
+            _ = self.signal.ready() => {}
+        }
+        Ok(())
+    }
+}
+
+impl RetryPolicy {
+    /// Like [`RetryPolicy::retry`], but between attempts wait for `gate` to signal readiness,
+    /// with the computed backoff as an upper bound.
+    ///
+    /// `gate` is a [`ReadyGate`] or anything implementing [`ReadySignal`], such as
+    /// `&tokio::sync::Notify` or a `tokio::sync::watch::Receiver<bool>`.
+    #[track_caller]
+    pub fn retry_when_ready<Fut, T, E, F, P, S>(
+        &self,
+        f: F,
+        should_retry: P,
+        gate: impl Into<ReadyGate<S>>,
+    ) -> impl Future<Output = Result<T, E>>
+    where
+        F: FnMut() -> Fut,
+        Fut: Future<Output = Result<T, E>>,
+        P: FnMut(&E) -> bool,
+        S: ReadySignal,
+    {
+        let site = Location::caller();
+        async move {
+            uninterrupted(
+                self.run_hooked(f, should_retry, site, &mut gate.into())
+                    .await,
+            )
+            .map(|(v, _)| v)
+            .map_err(|(e, _)| e)
+        }
+    }
+}
@@ -0,0 +1,27 @@
+//! Lending borrowed arguments to the attempts generated by `#[retry]`.
+//!
+//! An attempt's future cannot borrow from the `FnMut` closure that creates it, so arguments
+//! such as `&mut self` are moved into a [`Reborrow`] outside of the closure and every attempt
+//! takes exclusive access for as long as it runs.
+
+use futures::lock::{Mutex, MutexGuard};
+
+/// A value lent to one attempt at a time.
+#[doc(hidden)]
+pub struct Reborrow<T>(Mutex<T>);
+
+impl<T> Reborrow<T> {
+    pub fn new(value: T) -> Self {
+        Reborrow(Mutex::new(value))
+    }
+
+    /// Access for the attempt about to start.
+    ///
+    /// Panics if the previous attempt still holds it, which the retry loop never allows: every
+    /// attempt is finished or dropped before the next one is created.
+    pub fn lend(&self) -> MutexGuard<'_, T> {
+        self.0
+            .try_lock()
+            .expect("attempts of a retry sequence do not overlap")
+    }
+}
@@ -0,0 +1,172 @@
+//! Errors that classify themselves as transient or fatal.
+//!
+//! Implement [`Retryable`] (usually with `#[derive(Retryable)]`) and the error decides whether
+//! it is retried and, optionally, how long to wait first: [`RetryPolicy::retry_classified`]
+//! and `#[retry]` without a `predicate` use it automatically.
+
+use crate::predicate::{Always, RetryPredicate};
+use crate::{RetryPolicy, RetryStats};
+use std::future::Future;
+use std::marker::PhantomData;
+use std::panic::Location;
+use std::time::Duration;
+
+pub use asyn_retry_policy_macro::Retryable;
+
+/// An error that knows whether retrying it can succeed.
+///
+/// The derive macro implements it for enums from variant attributes:
+///
+/// ```
+/// use asyn_retry_policy::Retryable;
+/// use std::time::Duration;
+///
+/// #[derive(Debug, Retryable)]
+/// enum StoreError {
+///     #[retryable]
+///     Timeout,
+///     #[retryable(after_ms = 500)]
+///     Throttled,
+///     #[fatal]
+///     NotFound,
+///     #[retryable(from_source)]
+///     Io(IoError),
+/// }
+///
+/// #[derive(Debug, Retryable)]
+/// #[retryable(default = fatal)]
+/// enum IoError {
+///     #[retryable]
+///     Reset,
+///     PermissionDenied,
+/// }
+///
+/// assert!(StoreError::Throttled.is_retryable());
+/// assert_eq!(StoreError::Throttled.retry_after(), Some(Duration::from_millis(500)));
+/// assert!(!StoreError::NotFound.is_retryable());
+/// assert!(StoreError::Io(IoError::Reset).is_retryable());
+/// assert!(!StoreError::Io(IoError::PermissionDenied).is_retryable());
+/// ```
+///
+/// Variants without an attribute are a compile error unless the enum sets
+/// `#[retryable(default = retryable)]` or `#[retryable(default = fatal)]`:
+///
+/// ```compile_fail
+/// #[derive(asyn_retry_policy::Retryable)]
+/// enum Unclassified {
+///     #[retryable]
+///     Timeout,
+///     Forgotten,
+/// }
+/// ```
+pub trait Retryable {
+    /// Returns true if the error is transient and worth retrying.
+    fn is_retryable(&self) -> bool;
+
+    /// Delay before retrying this error, replacing the policy's backoff.
+    fn retry_after(&self) -> Option<Duration> {
+        None
+    }
+}
+
+impl<T: Retryable + ?Sized> Retryable for &T {
+    fn is_retryable(&self) -> bool {
+        (**self).is_retryable()
+    }
+
+    fn retry_after(&self) -> Option<Duration> {
+        (**self).retry_after()
+    }
+}
+
+impl<T: Retryable + ?Sized> Retryable for Box<T> {
+    fn is_retryable(&self) -> bool {
+        (**self).is_retryable()
+    }
+
+    fn retry_after(&self) -> Option<Duration> {
+        (**self).retry_after()
+    }
+}
+
+/// Predicate retrying the errors that classify themselves as retryable.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct IsRetryable;
+
+impl<E: Retryable + ?Sized> RetryPredicate<E> for IsRetryable {
+    fn should_retry(&mut self, error: &E) -> bool {
+        error.is_retryable()
+    }
+
+    fn retry_after(&mut self, error: &E) -> Option<Duration> {
+        error.retry_after()
+    }
+}
+
+impl RetryPolicy {
+    /// Like [`RetryPolicy::retry`], but the error decides whether it is retried and how long to
+    /// wait before the next attempt (see [`Retryable`]).
+    #[track_caller]
+    pub fn retry_classified<Fut, T, E, F>(&self, f: F) -> impl Future<Output = Result<T, E>>
+    where
+        F: FnMut() -> Fut,
+        Fut: Future<Output = Result<T, E>>,
+        E: Retryable,
+    {
+        self.retry_with(f, IsRetryable)
+    }
+}
+
+/// Selects the predicate `#[retry]` uses when none is given: [`IsRetryable`] for errors
+/// implementing [`Retryable`], [`Always`] otherwise.
+#[doc(hidden)]
+pub struct DefaultPredicate<E>(PhantomData<fn(&E)>);
+
+impl<E> Default for DefaultPredicate<E> {
+    fn default() -> Self {
+        DefaultPredicate(PhantomData)
+    }
+}
+
+#[doc(hidden)]
+pub trait ViaRetryable {
+    fn predicate(&self) -> IsRetryable {
+        IsRetryable
+    }
+}
+
+impl<E: Retryable> ViaRetryable for &DefaultPredicate<E> {}
+
+#[doc(hidden)]
+pub trait ViaAlways {
+    fn predicate(&self) -> Always {
+        Always
+    }
+}
+
+impl<E> ViaAlways for DefaultPredicate<E> {}
+
+/// [`RetryPolicy::retry_or_else`] for any [`RetryPredicate`]; used by `#[retry]`.
+#[doc(hidden)]
+#[track_caller]
+pub fn retry_or_else_with<Fut, T, E, F, P, FB, FbFut>(
+    policy: &RetryPolicy,
+    f: F,
+    should_retry: P,
+    fallback: FB,
+) -> impl Future<Output = Result<T, E>>
+where
+    F: FnMut() -> Fut,
+    Fut: Future<Output = Result<T, E>>,
+    P: RetryPredicate<E>,
+    FB: FnOnce(E, RetryStats) -> FbFut,
+    FbFut: Future<Output = Result<T, E>>,
+{
+    let site = Location::caller();
+    async move {
+        match policy.run(f, should_retry, site).await {
+            Ok((v, _)) => Ok(v),
+            Err((e, stats)) => fallback(e, stats).await,
+        }
+    }
+}
@@ -0,0 +1,133 @@
+//! Multi-step workflows with per-step retries and compensation.
+//!
+//! A [`Saga`] runs its steps in order, retrying each forward action with the step's own
+//! [`RetryPolicy`]. When a step finally fails, the compensating actions of the steps that
+//! already completed run in reverse order, also with retries, and a [`SagaError`] reports
+//! which step failed and how every compensation went.
+
+use crate::RetryPolicy;
+use futures::future::BoxFuture;
+use std::future::Future;
+use std::sync::Arc;
+
+type Action<E> = Box<dyn FnMut() -> BoxFuture<'static, Result<(), E>> + Send>;
+
+struct Step<E> {
+    name: &'static str,
+    policy: RetryPolicy,
+    forward: Action<E>,
+    compensate: Action<E>,
+}
+
+/// Outcome of a compensating action run after a saga failure.
+#[derive(Debug, PartialEq, Eq)]
+pub struct Compensation<E> {
+    /// Name of the step being compensated
+    pub step: &'static str,
+    /// Result of its compensating action after retries
+    pub result: Result<(), E>,
+}
+
+/// A failed saga: the step that failed and the compensations that ran.
+#[derive(Debug, thiserror::Error)]
+#[error("saga step `{failed_step}` failed: {error}")]
+pub struct SagaError<E> {
+    /// Name of the step whose forward action failed
+    pub failed_step: &'static str,
+    /// The step's final error
+    pub error: E,
+    /// Compensations of the completed steps, in the order they ran (reverse step order)
+    pub compensations: Vec<Compensation<E>>,
+}
+
+impl<E> SagaError<E> {
+    /// Returns true if every completed step was compensated successfully.
+    pub fn fully_compensated(&self) -> bool {
+        self.compensations.iter().all(|c| c.result.is_ok())
+    }
+}
+
+/// Builder for a multi-step workflow with compensation.
+pub struct Saga<E> {
+    steps: Vec<Step<E>>,
+    should_retry: Arc<dyn Fn(&E) -> bool + Send + Sync>,
+}
+
+impl<E: Send + 'static> Default for Saga<E> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<E: Send + 'static> Saga<E> {
+    /// Create an empty saga that retries every error.
+    pub fn new() -> Self {
+        Self {
+            steps: Vec::new(),
+            should_retry: Arc::new(|_| true),
+        }
+    }
+
+    /// Only retry errors (of forward and compensating actions) accepted by `should_retry`.
+    pub fn retry_if(mut self, should_retry: impl Fn(&E) -> bool + Send + Sync + 'static) -> Self {
+        self.should_retry = Arc::new(should_retry);
+        self
+    }
+
+    /// Append a step with a forward action, its compensating action and its own policy.
+    pub fn step<F, Fut, C, CFut>(
+        mut self,
+        name: &'static str,
+        policy: RetryPolicy,
+        mut forward: F,
+        mut compensate: C,
+    ) -> Self
+    where
+        F: FnMut() -> Fut + Send + 'static,
+        Fut: Future<Output = Result<(), E>> + Send + 'static,
+        C: FnMut() -> CFut + Send + 'static,
+        CFut: Future<Output = Result<(), E>> + Send + 'static,
+    {
+        self.steps.push(Step {
+            name,
+            policy,
+            forward: Box::new(move || Box::pin(forward())),
+            compensate: Box::new(move || Box::pin(compensate())),
+        });
+        self
+    }
+
+    /// Run the steps in order, compensating completed steps if one fails.
+    pub async fn run(self) -> Result<(), SagaError<E>> {
+        let should_retry = self.should_retry;
+        let mut completed = Vec::new();
+        for mut step in self.steps {
+            match step
+                .policy
+                .retry(&mut step.forward, |e| should_retry(e))
+                .await
+            {
+                Ok(()) => completed.push(step),
+                Err(error) => {
+                    let mut compensations = Vec::new();
+                    for mut done in completed.into_iter().rev() {
+                        let result = done
+                            .policy
+                            .retry(&mut done.compensate, |e| should_retry(e))
+                            .await;
+                        compensations.push(Compensation {
+                            step: done.name,
+                            result,
+                        });
+                    }
+                    return Err(SagaError {
+                        failed_step: step.name,
+                        error,
+                        compensations,
+                    });
+                }
+            }
+        }
+        Ok(())
+    }
+}
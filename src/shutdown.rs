@@ -0,0 +1,259 @@
+//! Graceful shutdown of in-flight retry sequences.
+//!
+//! Policies registered with a [`ShutdownCoordinator`] notice when the process shuts down.
+//! [`ShutdownCoordinator::drain`] lets their sequences finish within a grace period, skipping
+//! any remaining backoff sleeps; [`ShutdownCoordinator::fail_fast`] stops them at their next
+//! backoff instead. Both report how the sequences ended.
+//!
+//! An interrupted sequence fails with its last error from [`RetryPolicy::retry`] and plain
+//! `#[retry]` functions. [`RetryPolicy::retry_until_shutdown`], `#[retry(until_shutdown)]` and
+//! the other variants returning [`RetryError`] report it as `RetryError::ShuttingDown` instead.
+
+use crate::{Halt, RetryError, RetryPolicy, RetryPredicate, Sleep};
+use std::future::Future;
+use std::panic::Location;
+use std::sync::{Arc, Mutex, OnceLock};
+use std::time::Duration;
+use tokio::sync::{Notify, watch};
+use tokio::time::Instant;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Phase {
+    Running,
+    /// Skip backoff sleeps until `until`, then interrupt
+    Draining {
+        until: Instant,
+    },
+    FailingFast,
+}
+
+#[derive(Debug, Default)]
+struct Counts {
+    active: usize,
+    interrupted: usize,
+    completed: usize,
+}
+
+#[derive(Debug)]
+struct Inner {
+    phase: watch::Sender<Phase>,
+    counts: Mutex<Counts>,
+    idle: Notify,
+}
+
+/// How the sequences registered with a [`ShutdownCoordinator`] ended during shutdown.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct ShutdownReport {
+    /// Sequences stopped with a `ShuttingDown` outcome instead of retrying
+    pub interrupted: usize,
+    /// Sequences that finished on their own (success or final failure) after shutdown began
+    pub completed: usize,
+    /// Sequences still running an attempt when the grace period ran out
+    pub still_running: usize,
+}
+
+/// Coordinates the shutdown of every retry sequence using a registered policy.
+///
+/// Clones share the same state; [`ShutdownCoordinator::global`] provides a process-wide
+/// instance.
+#[derive(Clone, Debug)]
+pub struct ShutdownCoordinator {
+    inner: Arc<Inner>,
+}
+
+impl Default for ShutdownCoordinator {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl ShutdownCoordinator {
+    /// Create a coordinator that is not shutting down.
+    pub fn new() -> Self {
+        Self {
+            inner: Arc::new(Inner {
+                phase: watch::Sender::new(Phase::Running),
+                counts: Mutex::new(Counts::default()),
+                idle: Notify::new(),
+            }),
+        }
+    }
+
+    /// The process-wide coordinator.
+    pub fn global() -> &'static ShutdownCoordinator {
+        static GLOBAL: OnceLock<ShutdownCoordinator> = OnceLock::new();
+        GLOBAL.get_or_init(ShutdownCoordinator::new)
+    }
+
+    /// Register `policy`: sequences run with the returned policy observe this coordinator.
+    pub fn register(&self, mut policy: RetryPolicy) -> RetryPolicy {
+        policy.shutdown = Some(self.clone());
+        policy
+    }
+
+    /// Number of sequences currently running with a registered policy.
+    pub fn active(&self) -> usize {
+        self.inner.counts.lock().unwrap().active
+    }
+
+    /// Returns true once [`drain`](Self::drain) or [`fail_fast`](Self::fail_fast) was called.
+    ///
+    /// A sequence that failed while this is true may have failed on its own or been
+    /// interrupted; use [`RetryPolicy::retry_until_shutdown`] to know which.
+    pub fn is_shutting_down(&self) -> bool {
+        *self.inner.phase.borrow() != Phase::Running
+    }
+
+    /// Let running sequences finish, retrying without backoff, for at most `grace`.
+    ///
+    /// Sequences that would still back off after the grace period are interrupted.
+    pub async fn drain(&self, grace: Duration) -> ShutdownReport {
+        let until = Instant::now() + grace;
+        self.inner.phase.send_if_modified(|phase| {
+            if *phase == Phase::Running {
+                *phase = Phase::Draining { until };
+                true
+            } else {
+                false
+            }
+        });
+        self.wait_idle(until).await
+    }
+
+    /// Interrupt every sequence at its next backoff, waiting at most `grace` for attempts that
+    /// are still in flight.
+    pub async fn fail_fast(&self, grace: Duration) -> ShutdownReport {
+        self.inner.phase.send_replace(Phase::FailingFast);
+        self.wait_idle(Instant::now() + grace).await
+    }
+
+    async fn wait_idle(&self, deadline: Instant) -> ShutdownReport {
+        loop {
+            let idle = self.inner.idle.notified();
+            tokio::pin!(idle);
+            idle.as_mut().enable();
+            if self.active() == 0 {
+                break;
+            }
+            tokio::select! {
+                _ = idle => {}
+                _ = tokio::time::sleep_until(deadline) => break,
+            }
+        }
+        let counts = self.inner.counts.lock().unwrap();
+        ShutdownReport {
+            interrupted: counts.interrupted,
+            completed: counts.completed,
+            still_running: counts.active,
+        }
+    }
+
+    /// Track a sequence until the returned registration is dropped.
+    pub(crate) fn enter(&self) -> Registration {
+        self.inner.counts.lock().unwrap().active += 1;
+        Registration {
+            inner: self.inner.clone(),
+            phase: self.inner.phase.subscribe(),
+            interrupted: false,
+        }
+    }
+}
+
+impl RetryPolicy {
+    /// Like [`RetryPolicy::retry`], but reports a sequence interrupted by the policy's
+    /// [`ShutdownCoordinator`] as [`RetryError::ShuttingDown`] rather than as its last error.
+    ///
+    /// Any other failure is returned as [`RetryError::Failed`]. `#[retry(until_shutdown)]`
+    /// does the same for functions returning `Result<T, RetryError<E>>`.
+    #[track_caller]
+    pub fn retry_until_shutdown<Fut, T, E, F, P>(
+        &self,
+        f: F,
+        should_retry: P,
+    ) -> impl Future<Output = Result<T, RetryError<E>>>
+    where
+        F: FnMut() -> Fut,
+        Fut: Future<Output = Result<T, E>>,
+        P: FnMut(&E) -> bool,
+    {
+        retry_until_shutdown_with(self, f, should_retry)
+    }
+}
+
+/// [`RetryPolicy::retry_until_shutdown`] for any [`RetryPredicate`]; used by `#[retry]`.
+#[doc(hidden)]
+#[track_caller]
+pub fn retry_until_shutdown_with<Fut, T, E, F, P>(
+    policy: &RetryPolicy,
+    f: F,
+    should_retry: P,
+) -> impl Future<Output = Result<T, RetryError<E>>>
+where
+    F: FnMut() -> Fut,
+    Fut: Future<Output = Result<T, E>>,
+    P: RetryPredicate<E>,
+{
+    let site = Location::caller();
+    async move {
+        match policy.run_hooked(f, should_retry, site, &mut Sleep).await {
+            Ok((v, _)) => Ok(v),
+            Err((Halt::ShuttingDown(last_error), stats)) => {
+                Err(RetryError::ShuttingDown { last_error, stats })
+            }
+            Err((Halt::Failed(e), _)) => Err(RetryError::Failed(e)),
+            Err((Halt::Interrupted(never, _), _)) => match never {},
+        }
+    }
+}
+
+/// Marker for a sequence stopped because of a shutdown.
+pub(crate) struct ShuttingDown;
+
+/// A running sequence registered with a [`ShutdownCoordinator`].
+pub(crate) struct Registration {
+    inner: Arc<Inner>,
+    phase: watch::Receiver<Phase>,
+    interrupted: bool,
+}
+
+impl Registration {
+    /// Run the backoff `sleep` unless a shutdown skips it (`Ok(None)`) or interrupts it.
+    pub(crate) async fn wait<R>(
+        &mut self,
+        sleep: impl Future<Output = R>,
+    ) -> Result<Option<R>, ShuttingDown> {
+        tokio::pin!(sleep);
+        loop {
+            match *self.phase.borrow_and_update() {
+                Phase::Running => {}
+                Phase::Draining { until } if Instant::now() < until => return Ok(None),
+                Phase::Draining { .. } | Phase::FailingFast => {
+                    self.interrupted = true;
+                    return Err(ShuttingDown);
+                }
+            }
+            tokio::select! {
+                r = &mut sleep => return Ok(Some(r)),
+                // the sender lives in `inner`, so the channel never closes
+                _ = self.phase.changed() => {}
+            }
+        }
+    }
+}
+
+impl Drop for Registration {
+    fn drop(&mut self) {
+        let mut counts = self.inner.counts.lock().unwrap();
+        counts.active -= 1;
+        if *self.phase.borrow() != Phase::Running {
+            if self.interrupted {
+                counts.interrupted += 1;
+            } else {
+                counts.completed += 1;
+            }
+        }
+        if counts.active == 0 {
+            self.inner.idle.notify_waiters();
+        }
+    }
+}
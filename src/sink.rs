@@ -0,0 +1,198 @@
+//! A `futures::Sink` adapter that retries failed sends with backoff.
+//!
+//! [`RetrySink`] buffers items that the inner sink failed to take, waits for the
+//! [`RetryPolicy`] backoff, re-polls (or re-creates) the inner sink and sends them again. An
+//! error is only surfaced once the policy is exhausted; the items not delivered by then stay
+//! buffered.
+
+use crate::RetryPolicy;
+use futures::Sink;
+use std::collections::VecDeque;
+use std::pin::Pin;
+use std::task::{Context, Poll, ready};
+use tokio::time::Sleep;
+
+/// Sink wrapper retrying `poll_ready`, `start_send` and `poll_flush` failures of an inner sink.
+///
+/// By default items are buffered: an item is kept until the inner sink accepts it through
+/// `start_send` (a failing `start_send` is handed a clone), so only items the sink never took
+/// are sent again. With [`RetrySink::clone_items`] a copy of every sent item is also kept until
+/// the next successful flush, so items lost by a failing flush are re-sent as well.
+///
+/// The retry count is reset by a successful flush. Once the policy is exhausted the error is
+/// returned and the undelivered items stay buffered: the next `poll_ready` or flush tries them
+/// again with a fresh retry count, or [`RetrySink::take_pending`] removes them. Only the
+/// `attempts` and backoff fields of the policy apply; `max_elapsed`, `shutdown` and `on_nested`
+/// are ignored.
+pub struct RetrySink<S, Item, M = fn() -> S> {
+    policy: RetryPolicy,
+    make: Option<M>,
+    sink: Option<S>,
+    clone_items: bool,
+    pending: VecDeque<Item>,
+    unflushed: Vec<Item>,
+    attempt: usize,
+    sleep: Option<Pin<Box<Sleep>>>,
+}
+
+// Items are never pinned, so the wrapper is `Unpin` whenever the inner sink is.
+impl<S: Unpin, Item, M> Unpin for RetrySink<S, Item, M> {}
+
+impl<S, Item> RetrySink<S, Item> {
+    /// Wrap `sink`, re-polling the same sink after a failure.
+    pub fn new(policy: RetryPolicy, sink: S) -> Self {
+        Self::build(policy, None, Some(sink))
+    }
+}
+
+impl<S, Item, M> RetrySink<S, Item, M>
+where
+    M: FnMut() -> S,
+{
+    /// Create the inner sink with `make`, dropping and re-creating it after every failure.
+    pub fn reconnecting(policy: RetryPolicy, make: M) -> Self {
+        Self::build(policy, Some(make), None)
+    }
+}
+
+impl<S, Item, M> RetrySink<S, Item, M> {
+    fn build(policy: RetryPolicy, make: Option<M>, sink: Option<S>) -> Self {
+        Self {
+            policy,
+            make,
+            sink,
+            clone_items: false,
+            pending: VecDeque::new(),
+            unflushed: Vec::new(),
+            attempt: 1,
+            sleep: None,
+        }
+    }
+
+    /// Keep a clone of every sent item until the next successful flush and re-send the
+    /// unflushed items after a failure.
+    pub fn clone_items(mut self) -> Self {
+        self.clone_items = true;
+        self
+    }
+
+    /// The inner sink, if it is currently connected.
+    pub fn get_ref(&self) -> Option<&S> {
+        self.sink.as_ref()
+    }
+
+    /// Number of items waiting to be (re-)sent.
+    pub fn pending(&self) -> usize {
+        self.pending.len()
+    }
+
+    /// Remove and return the items waiting to be (re-)sent, e.g. after the policy was exhausted.
+    pub fn take_pending(&mut self) -> Vec<Item> {
+        self.pending.drain(..).collect()
+    }
+
+    /// Record a failure: schedule a retry, or give up and return `err` once exhausted, keeping
+    /// the undelivered items for a later attempt.
+    fn fail<E>(&mut self, err: E) -> Result<(), E> {
+        // items the inner sink took but did not flush are re-sent ahead of the rest
+        for item in self.unflushed.drain(..).rev() {
+            self.pending.push_front(item);
+        }
+        if self.make.is_some() {
+            self.sink = None;
+        }
+        if self.attempt >= self.policy.attempts {
+            self.attempt = 1;
+            return Err(err);
+        }
+        let delay = self.policy.jittered_delay(self.attempt);
+        self.attempt += 1;
+        self.sleep = Some(Box::pin(tokio::time::sleep(delay)));
+        Ok(())
+    }
+}
+
+impl<S, Item, M> RetrySink<S, Item, M>
+where
+    S: Sink<Item> + Unpin,
+    Item: Clone,
+    M: FnMut() -> S,
+{
+    /// Push all pending items into the inner sink, retrying failures with backoff.
+    fn poll_deliver(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), S::Error>> {
+        loop {
+            if let Some(sleep) = &mut self.sleep {
+                ready!(sleep.as_mut().poll(cx));
+                self.sleep = None;
+            }
+            if self.pending.is_empty() {
+                return Poll::Ready(Ok(()));
+            }
+            let sink = match (&mut self.sink, &mut self.make) {
+                (Some(sink), _) => sink,
+                (None, Some(make)) => self.sink.insert(make()),
+                (None, None) => unreachable!("a sink without `make` is never dropped"),
+            };
+            if let Err(e) = ready!(Pin::new(&mut *sink).poll_ready(cx)) {
+                self.fail(e)?;
+                continue;
+            }
+            let item = self.pending.pop_front().expect("pending is not empty");
+            let copy = item.clone();
+            match Pin::new(sink).start_send(item) {
+                Ok(()) if self.clone_items => self.unflushed.push(copy),
+                Ok(()) => {}
+                Err(e) => {
+                    self.pending.push_front(copy);
+                    self.fail(e)?;
+                }
+            }
+        }
+    }
+}
+
+impl<S, Item, M> Sink<Item> for RetrySink<S, Item, M>
+where
+    S: Sink<Item> + Unpin,
+    Item: Clone,
+    M: FnMut() -> S,
+{
+    type Error = S::Error;
+
+    fn poll_ready(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.get_mut().poll_deliver(cx)
+    }
+
+    fn start_send(self: Pin<&mut Self>, item: Item) -> Result<(), Self::Error> {
+        self.get_mut().pending.push_back(item);
+        Ok(())
+    }
+
+    fn poll_flush(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        let this = self.get_mut();
+        loop {
+            ready!(this.poll_deliver(cx))?;
+            let Some(sink) = &mut this.sink else {
+                this.attempt = 1;
+                return Poll::Ready(Ok(()));
+            };
+            match ready!(Pin::new(sink).poll_flush(cx)) {
+                Ok(()) => {
+                    this.unflushed.clear();
+                    this.attempt = 1;
+                    return Poll::Ready(Ok(()));
+                }
+                Err(e) => this.fail(e)?,
+            }
+        }
+    }
+
+    fn poll_close(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        let this = self.get_mut();
+        ready!(Pin::new(&mut *this).poll_flush(cx))?;
+        match &mut this.sink {
+            Some(sink) => Pin::new(sink).poll_close(cx),
+            None => Poll::Ready(Ok(())),
+        }
+    }
+}
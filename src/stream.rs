@@ -0,0 +1,190 @@
+//! Retry helpers for `futures` streams.
+//!
+//! [`retry_stream`] keeps a long-lived stream (change feeds, SSE-style feeds) flowing by
+//! re-creating it with backoff whenever it yields an error, resuming from the last item seen.
+//! [`RetryStreamExt`] retries the processing of each item of a `TryStream` independently.
+
+use crate::{RetryPolicy, RetryStats};
+use futures::{Stream, StreamExt, TryStream, TryStreamExt};
+use std::future::Future;
+use std::panic::Location;
+use std::pin::Pin;
+use std::sync::Arc;
+
+struct Resubscribe<St, Tok, M, K, P> {
+    policy: RetryPolicy,
+    make: M,
+    resume_token: K,
+    should_retry: P,
+    token: Option<Tok>,
+    stream: Option<Pin<Box<St>>>,
+    attempt: usize,
+    done: bool,
+}
+
+/// Consume the stream built by `make`, re-creating it with backoff when it yields an error.
+///
+/// `make` receives the resume token of the last item yielded so far (`None` for the initial
+/// subscription), and `resume_token` extracts that token from each item. Once an item flows
+/// the attempt counter resets. When the policy is exhausted or `should_retry` rejects an error,
+/// the error is yielded and the stream ends; the stream also ends when the inner stream does.
+///
+/// Only `attempts` and the backoff fields of the policy apply; `max_elapsed`, `shutdown` and
+/// `on_nested` are ignored, since the stream is meant to run indefinitely. [`RetryStreamExt`]
+/// runs full retry sequences and honors them.
+pub fn retry_stream<St, T, E, Tok, M, K, P>(
+    policy: RetryPolicy,
+    make: M,
+    resume_token: K,
+    should_retry: P,
+) -> impl Stream<Item = Result<T, E>>
+where
+    St: Stream<Item = Result<T, E>>,
+    Tok: Clone,
+    M: FnMut(Option<Tok>) -> St,
+    K: FnMut(&T) -> Tok,
+    P: FnMut(&E) -> bool,
+{
+    let state = Resubscribe {
+        policy,
+        make,
+        resume_token,
+        should_retry,
+        token: None,
+        stream: None,
+        attempt: 1,
+        done: false,
+    };
+    futures::stream::unfold(state, |mut st| async move {
+        if st.done {
+            return None;
+        }
+        loop {
+            let stream = match &mut st.stream {
+                Some(stream) => stream,
+                None => st.stream.insert(Box::pin((st.make)(st.token.clone()))),
+            };
+            match stream.next().await {
+                Some(Ok(item)) => {
+                    st.token = Some((st.resume_token)(&item));
+                    st.attempt = 1;
+                    return Some((Ok(item), st));
+                }
+                Some(Err(e)) if st.attempt < st.policy.attempts && (st.should_retry)(&e) => {
+                    st.stream = None;
+                    tokio::time::sleep(st.policy.jittered_delay(st.attempt)).await;
+                    st.attempt += 1;
+                }
+                Some(Err(e)) => {
+                    st.done = true;
+                    return Some((Err(e), st));
+                }
+                None => return None,
+            }
+        }
+    })
+}
+
+/// Result of processing a single stream item with [`RetryStreamExt`].
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ItemOutcome<T, E> {
+    /// Final result of processing the item, or the error yielded by the source stream
+    pub result: Result<T, E>,
+    /// Attempts made for this item; `attempts` is 0 for errors of the source stream
+    pub stats: RetryStats,
+}
+
+/// Per-item retries for `TryStream` processing pipelines.
+///
+/// Each `Ok` item is processed by `f`, retrying with `policy` independently of the other items,
+/// so one flaky item does not end the pipeline. Up to `limit` items are processed concurrently.
+pub trait RetryStreamExt: TryStream + Sized {
+    /// Process items concurrently, yielding outcomes in completion order
+    /// (like `buffer_unordered`).
+    #[track_caller]
+    fn retry_each<F, Fut, T, P>(
+        self,
+        policy: RetryPolicy,
+        limit: usize,
+        f: F,
+        should_retry: P,
+    ) -> impl Stream<Item = ItemOutcome<T, Self::Error>>
+    where
+        Self::Ok: Clone,
+        F: Fn(Self::Ok) -> Fut,
+        Fut: Future<Output = Result<T, Self::Error>>,
+        P: Fn(&Self::Error) -> bool,
+    {
+        process_each(self, policy, f, should_retry).buffer_unordered(limit)
+    }
+
+    /// Process items concurrently, yielding outcomes in the order of the source stream
+    /// (like `buffered`).
+    #[track_caller]
+    fn retry_each_ordered<F, Fut, T, P>(
+        self,
+        policy: RetryPolicy,
+        limit: usize,
+        f: F,
+        should_retry: P,
+    ) -> impl Stream<Item = ItemOutcome<T, Self::Error>>
+    where
+        Self::Ok: Clone,
+        F: Fn(Self::Ok) -> Fut,
+        Fut: Future<Output = Result<T, Self::Error>>,
+        P: Fn(&Self::Error) -> bool,
+    {
+        process_each(self, policy, f, should_retry).buffered(limit)
+    }
+}
+
+impl<S: TryStream> RetryStreamExt for S {}
+
+/// Map every item to a future running its own retry sequence.
+#[track_caller]
+fn process_each<S, F, Fut, T, P>(
+    stream: S,
+    policy: RetryPolicy,
+    f: F,
+    should_retry: P,
+) -> impl Stream<Item = impl Future<Output = ItemOutcome<T, S::Error>>>
+where
+    S: TryStream,
+    S::Ok: Clone,
+    F: Fn(S::Ok) -> Fut,
+    Fut: Future<Output = Result<T, S::Error>>,
+    P: Fn(&S::Error) -> bool,
+{
+    let f = Arc::new(f);
+    let should_retry = Arc::new(should_retry);
+    let site = Location::caller();
+    stream.into_stream().map(move |res| {
+        let policy = policy.clone();
+        let f = f.clone();
+        let should_retry = should_retry.clone();
+        async move {
+            let item = match res {
+                Ok(item) => item,
+                Err(e) => {
+                    return ItemOutcome {
+                        result: Err(e),
+                        stats: RetryStats::default(),
+                    };
+                }
+            };
+            match policy
+                .run(|| f(item.clone()), |e: &_| should_retry(e), site)
+                .await
+            {
+                Ok((v, stats)) => ItemOutcome {
+                    result: Ok(v),
+                    stats,
+                },
+                Err((e, stats)) => ItemOutcome {
+                    result: Err(e),
+                    stats,
+                },
+            }
+        }
+    })
+}
@@ -0,0 +1,144 @@
+//! Restart long-running async tasks with backoff.
+//!
+//! A supervised task is expected to run forever: its future finishing, with an error or
+//! otherwise, counts as a crash. The [`Supervisor`] restarts it using the backoff of a
+//! [`RetryPolicy`], forgets previous crashes once the task stayed up for `min_uptime`, and can
+//! escalate instead of flapping forever when restarts exceed a restart intensity limit.
+
+use crate::{BackoffState, RetryPolicy};
+use std::collections::VecDeque;
+use std::future::Future;
+use std::time::Duration;
+use tokio::time::Instant;
+
+/// Why a supervisor stopped restarting its task.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum StopReason {
+    /// The shutdown signal fired
+    Shutdown,
+    /// The task crashed `policy.attempts` times in a row without reaching `min_uptime`
+    Exhausted,
+    /// More restarts than allowed happened within the restart intensity window
+    IntensityExceeded,
+}
+
+/// Outcome of a supervised run.
+#[derive(Clone, Debug)]
+pub struct SupervisorReport<T> {
+    /// Why supervision ended
+    pub reason: StopReason,
+    /// Number of times the task was restarted
+    pub restarts: usize,
+    /// Output of the last run that finished, if any
+    pub last_exit: Option<T>,
+    /// Wall-clock time spent supervising
+    pub elapsed: Duration,
+}
+
+/// Restarts a task with [`RetryPolicy`] backoff whenever it finishes.
+///
+/// Only `attempts` and the backoff fields of the policy apply; `max_elapsed`, `shutdown` and
+/// `on_nested` are ignored. Supervision ends through the `shutdown` future passed to
+/// [`Supervisor::run`] instead.
+#[derive(Clone, Debug)]
+pub struct Supervisor {
+    policy: RetryPolicy,
+    min_uptime: Duration,
+    intensity: Option<(usize, Duration)>,
+}
+
+impl Supervisor {
+    /// Create a supervisor using `policy` for restart delays.
+    ///
+    /// `policy.attempts` bounds consecutive crashes; use `usize::MAX` to restart indefinitely.
+    pub fn new(policy: RetryPolicy) -> Self {
+        Self {
+            policy,
+            min_uptime: Duration::from_secs(60),
+            intensity: None,
+        }
+    }
+
+    /// Reset the backoff once a run lasted at least `uptime` (defaults to 60s).
+    pub fn min_uptime(mut self, uptime: Duration) -> Self {
+        self.min_uptime = uptime;
+        self
+    }
+
+    /// Give up with [`StopReason::IntensityExceeded`] when more than `max_restarts` restarts
+    /// happen within `window`.
+    pub fn restart_intensity(mut self, max_restarts: usize, window: Duration) -> Self {
+        self.intensity = Some((max_restarts, window));
+        self
+    }
+
+    /// Run the task produced by `f`, restarting it until `shutdown` completes or a limit is hit.
+    pub async fn run<F, Fut, T, S>(&self, mut f: F, shutdown: S) -> SupervisorReport<T>
+    where
+        F: FnMut() -> Fut,
+        Fut: Future<Output = T>,
+        S: Future<Output = ()>,
+    {
+        let start = Instant::now();
+        let mut backoff = BackoffState::new(self.policy.clone());
+        let mut recent_restarts = VecDeque::new();
+        let mut report = SupervisorReport {
+            reason: StopReason::Shutdown,
+            restarts: 0,
+            last_exit: None,
+            elapsed: Duration::ZERO,
+        };
+        tokio::pin!(shutdown);
+
+        loop {
+            let run_start = Instant::now();
+            tokio::select! {
+                out = f() => report.last_exit = Some(out),
+                _ = &mut shutdown => break,
+            }
+            if run_start.elapsed() >= self.min_uptime {
+                backoff.reset();
+            }
+
+            if let Some((max_restarts, window)) = self.intensity {
+                let now = Instant::now();
+                while recent_restarts
+                    .front()
+                    .is_some_and(|at| now.duration_since(*at) > window)
+                {
+                    recent_restarts.pop_front();
+                }
+                if recent_restarts.len() >= max_restarts {
+                    report.reason = StopReason::IntensityExceeded;
+                    break;
+                }
+                recent_restarts.push_back(now);
+            }
+
+            let Some(delay) = backoff.next_delay() else {
+                report.reason = StopReason::Exhausted;
+                break;
+            };
+            tokio::select! {
+                _ = tokio::time::sleep(delay) => {}
+                _ = &mut shutdown => break,
+            }
+            report.restarts += 1;
+        }
+
+        report.elapsed = start.elapsed();
+        report
+    }
+}
+
+/// Restart the task produced by `f` with `policy` backoff until the policy is exhausted.
+///
+/// Shorthand for `Supervisor::new(policy).run(f, std::future::pending())`; use
+/// [`Supervisor`] for a shutdown signal, `min_uptime` or restart intensity limits.
+pub async fn supervise<F, Fut, T>(policy: RetryPolicy, f: F) -> SupervisorReport<T>
+where
+    F: FnMut() -> Fut,
+    Fut: Future<Output = T>,
+{
+    Supervisor::new(policy).run(f, std::future::pending()).await
+}
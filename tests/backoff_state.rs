@@ -0,0 +1,96 @@
+use asyn_retry_policy::{BackoffState, RetryPolicy};
+use std::time::Duration;
+
+#[test]
+fn steps_through_policy_delays_until_exhausted() {
+    let mut backoff = BackoffState::new(RetryPolicy {
+        attempts: 4,
+        base_delay: Duration::from_millis(100),
+        max_delay: Duration::from_secs(1),
+        jitter: false,
+        ..Default::default()
+    });
+    assert_eq!(backoff.next_delay(), Some(Duration::from_millis(100)));
+    assert_eq!(backoff.next_delay(), Some(Duration::from_millis(200)));
+    assert_eq!(backoff.next_delay(), Some(Duration::from_millis(400)));
+    assert_eq!(backoff.next_delay(), None);
+    assert_eq!(backoff.attempt(), 3);
+
+    backoff.reset();
+    assert_eq!(backoff.next_delay(), Some(Duration::from_millis(100)));
+}
+
+#[test]
+fn seeded_jitter_matches_policy() {
+    let policy = RetryPolicy {
+        attempts: 4,
+        base_delay: Duration::from_millis(100),
+        max_delay: Duration::from_secs(1),
+        rng_seed: Some(7),
+        ..Default::default()
+    };
+    let mut backoff = BackoffState::from(policy.clone());
+    assert_eq!(backoff.next_delay(), Some(policy.jittered_delay(1)));
+    assert_eq!(backoff.next_delay(), Some(policy.jittered_delay(2)));
+}
+
+#[tokio::test]
+async fn resets_after_healthy_period() {
+    tokio::time::pause();
+    let mut backoff = BackoffState::new(RetryPolicy {
+        attempts: 4,
+        base_delay: Duration::from_millis(100),
+        max_delay: Duration::from_secs(1),
+        jitter: false,
+        ..Default::default()
+    })
+    .reset_after(Duration::from_secs(30));
+
+    backoff.next_delay();
+    backoff.next_delay();
+
+    // a short healthy period does not reset the counter
+    backoff.mark_healthy();
+    tokio::time::advance(Duration::from_secs(10)).await;
+    assert_eq!(backoff.next_delay(), Some(Duration::from_millis(400)));
+
+    backoff.reset();
+    backoff.next_delay();
+    backoff.mark_healthy();
+    tokio::time::advance(Duration::from_secs(30)).await;
+    assert_eq!(backoff.next_delay(), Some(Duration::from_millis(100)));
+}
+
+#[tokio::test]
+async fn wait_sleeps_and_reports_exhaustion() {
+    tokio::time::pause();
+    let mut backoff = BackoffState::new(RetryPolicy {
+        attempts: 2,
+        base_delay: Duration::from_millis(100),
+        jitter: false,
+        ..Default::default()
+    });
+    let start = tokio::time::Instant::now();
+    assert!(backoff.wait().await);
+    assert!(start.elapsed() >= Duration::from_millis(100));
+    assert!(!backoff.wait().await);
+}
+
+#[test]
+fn endless_policies_saturate_at_max_delay() {
+    let mut backoff = BackoffState::new(RetryPolicy {
+        attempts: usize::MAX,
+        base_delay: Duration::from_millis(100),
+        max_delay: Duration::from_secs(30),
+        jitter: false,
+        ..Default::default()
+    });
+    for _ in 0..500 {
+        assert!(backoff.next_delay().unwrap() <= Duration::from_secs(30));
+    }
+    assert_eq!(backoff.next_delay(), Some(Duration::from_secs(30)));
+    assert_eq!(
+        backoff.policy().compute_backoff(usize::MAX),
+        Duration::from_secs(30)
+    );
+}
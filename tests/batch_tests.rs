@@ -0,0 +1,121 @@
+use asyn_retry_policy::RetryPolicy;
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+#[tokio::test]
+async fn only_failed_items_are_resent() {
+    tokio::time::pause();
+    // item -> number of times it fails before succeeding
+    let failures = Arc::new(Mutex::new(HashMap::from([("b", 1), ("c", 1), ("d", 9)])));
+    let batches = Arc::new(Mutex::new(Vec::new()));
+
+    let outcomes = RetryPolicy {
+        attempts: 3,
+        base_delay: Duration::from_millis(10),
+        jitter: false,
+        ..Default::default()
+    }
+    .retry_batch(
+        vec!["a", "b", "c", "d", "e"],
+        |pending: Vec<&'static str>| {
+            let failures = failures.clone();
+            batches.lock().unwrap().push(pending.clone());
+            async move {
+                let mut failures = failures.lock().unwrap();
+                pending
+                    .into_iter()
+                    .map(|item| match failures.get_mut(item) {
+                        Some(0) | None => Ok(item.to_uppercase()),
+                        Some(left) => {
+                            *left -= 1;
+                            Err("busy")
+                        }
+                    })
+                    .collect()
+            }
+        },
+        |e| *e == "busy",
+    )
+    .await;
+
+    assert_eq!(
+        *batches.lock().unwrap(),
+        vec![
+            vec!["a", "b", "c", "d", "e"],
+            vec!["b", "c", "d"],
+            vec!["d"]
+        ]
+    );
+    let summary: Vec<_> = outcomes
+        .iter()
+        .map(|o| (o.result.clone(), o.stats.attempts))
+        .collect();
+    assert_eq!(
+        summary,
+        vec![
+            (Ok("A".to_string()), 1),
+            (Ok("B".to_string()), 2),
+            (Ok("C".to_string()), 2),
+            (Err("busy"), 3),
+            (Ok("E".to_string()), 1),
+        ]
+    );
+    // 10ms + 20ms of backoff, as measured by the timer
+    let total_delay = outcomes[3].stats.total_delay;
+    assert!(total_delay >= Duration::from_millis(30) && total_delay < Duration::from_millis(40));
+}
+
+#[tokio::test]
+async fn non_retryable_failures_are_not_resent() {
+    let calls = Arc::new(Mutex::new(0));
+    let outcomes = RetryPolicy {
+        attempts: 3,
+        base_delay: Duration::from_millis(10),
+        jitter: false,
+        ..Default::default()
+    }
+    .retry_batch(
+        vec![1, 2],
+        |pending: Vec<u8>| {
+            *calls.lock().unwrap() += 1;
+            async move { pending.into_iter().map(|_| Err::<u8, _>("fatal")).collect() }
+        },
+        |_| false,
+    )
+    .await;
+    assert_eq!(*calls.lock().unwrap(), 1);
+    assert!(outcomes.iter().all(|o| o.result.is_err()));
+}
+
+#[tokio::test]
+async fn max_elapsed_bounds_the_whole_batch() {
+    tokio::time::pause();
+    let calls = Arc::new(Mutex::new(0));
+    let outcomes = RetryPolicy {
+        attempts: 10,
+        base_delay: Duration::from_secs(1),
+        backoff_factor: 1.0,
+        jitter: false,
+        max_elapsed: Some(Duration::from_millis(2500)),
+        ..Default::default()
+    }
+    .retry_batch(
+        vec![1, 2],
+        |pending: Vec<u32>| {
+            *calls.lock().unwrap() += 1;
+            async move {
+                pending
+                    .into_iter()
+                    .map(|n| if n == 1 { Ok(n) } else { Err("busy") })
+                    .collect()
+            }
+        },
+        |_| true,
+    )
+    .await;
+    assert_eq!(*calls.lock().unwrap(), 3);
+    assert_eq!(outcomes[0].result, Ok(1));
+    assert_eq!(outcomes[1].result, Err("busy"));
+    assert_eq!(outcomes[1].stats.attempts, 3);
+}
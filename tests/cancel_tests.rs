@@ -0,0 +1,110 @@
+use asyn_retry_policy::{CancelOn, CancelToken, RetryError, RetryPolicy};
+use std::sync::Arc;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::time::Duration;
+
+fn cancel_after(token: &CancelToken, after: Duration) {
+    let token = token.clone();
+    tokio::spawn(async move {
+        tokio::time::sleep(after).await;
+        token.cancel();
+    });
+}
+
+#[tokio::test]
+async fn cancel_interrupts_backoff_with_last_error() {
+    tokio::time::pause();
+    let token = CancelToken::new();
+    cancel_after(&token, Duration::from_secs(15));
+    let start = tokio::time::Instant::now();
+
+    let calls = Arc::new(AtomicUsize::new(0));
+    let res = RetryPolicy {
+        attempts: 10,
+        base_delay: Duration::from_secs(10),
+        max_delay: Duration::from_secs(60),
+        jitter: false,
+        ..Default::default()
+    }
+    .retry_with_cancel(
+        || {
+            let n = calls.fetch_add(1, Ordering::SeqCst) + 1;
+            async move { Err::<(), _>(format!("failure {n}")) }
+        },
+        |_| true,
+        &token,
+    )
+    .await;
+
+    match res {
+        Err(RetryError::Cancelled { last_error, stats }) => {
+            assert_eq!(last_error.as_deref(), Some("failure 2"));
+            assert_eq!(stats.attempts, 2);
+        }
+        other => panic!("expected cancellation, got {other:?}"),
+    }
+    assert!(start.elapsed() < Duration::from_secs(16));
+}
+
+#[tokio::test]
+async fn in_flight_attempt_is_dropped_by_default() {
+    tokio::time::pause();
+    let token = CancelToken::new();
+    cancel_after(&token, Duration::from_secs(1));
+    let res = RetryPolicy {
+        attempts: 10,
+        base_delay: Duration::from_secs(10),
+        max_delay: Duration::from_secs(60),
+        jitter: false,
+        ..Default::default()
+    }
+    .retry_with_cancel(
+        || async {
+            tokio::time::sleep(Duration::from_secs(5)).await;
+            Ok::<_, &str>(1)
+        },
+        |_| true,
+        &token,
+    )
+    .await;
+    let err = res.unwrap_err();
+    assert!(err.last_error().is_none());
+    assert!(matches!(err, RetryError::Cancelled { stats, .. } if stats.attempts == 0));
+}
+
+#[tokio::test]
+async fn in_flight_attempt_can_finish() {
+    tokio::time::pause();
+    let token = CancelToken::new();
+    cancel_after(&token, Duration::from_secs(1));
+    let res = RetryPolicy {
+        attempts: 10,
+        base_delay: Duration::from_secs(10),
+        max_delay: Duration::from_secs(60),
+        jitter: false,
+        ..Default::default()
+    }
+    .retry_with_cancel(
+        || async {
+            tokio::time::sleep(Duration::from_secs(5)).await;
+            Ok::<_, &str>(1)
+        },
+        |_| true,
+        CancelOn::new(&token).finish_in_flight(),
+    )
+    .await;
+    assert_eq!(res.unwrap(), 1);
+}
+
+#[tokio::test]
+async fn exhaustion_is_reported_as_failed() {
+    let token = CancelToken::new();
+    let res = RetryPolicy {
+        attempts: 1,
+        ..Default::default()
+    }
+    .retry_with_cancel(|| async { Err::<(), _>("down") }, |_| true, &token)
+    .await;
+    assert!(matches!(res, Err(RetryError::Failed("down"))));
+    assert_eq!(res.unwrap_err().to_string(), "down");
+}
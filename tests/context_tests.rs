@@ -0,0 +1,176 @@
+use asyn_retry_policy::{RetryContext, RetryPolicy, remaining_budget, retry};
+use std::sync::Arc;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::time::Duration;
+use tokio::time::Instant;
+
+#[tokio::test]
+async fn max_elapsed_stops_before_sleeping_past_the_deadline() {
+    tokio::time::pause();
+    let start = Instant::now();
+    let calls = AtomicUsize::new(0);
+    let res = RetryPolicy {
+        attempts: 10,
+        base_delay: Duration::from_secs(10),
+        max_delay: Duration::from_secs(60),
+        backoff_factor: 1.0,
+        jitter: false,
+        max_elapsed: Some(Duration::from_secs(25)),
+        ..Default::default()
+    }
+    .retry(
+        || {
+            calls.fetch_add(1, Ordering::SeqCst);
+            async { Err::<(), _>("down") }
+        },
+        |_| true,
+    )
+    .await;
+    assert_eq!(res, Err("down"));
+    assert_eq!(calls.load(Ordering::SeqCst), 3);
+    assert!(start.elapsed() < Duration::from_secs(25));
+}
+
+#[tokio::test]
+async fn inner_retries_are_clamped_to_the_outer_deadline() {
+    tokio::time::pause();
+    let inner_calls = Arc::new(AtomicUsize::new(0));
+    let outer = RetryPolicy {
+        attempts: 1,
+        base_delay: Duration::from_secs(10),
+        max_delay: Duration::from_secs(60),
+        backoff_factor: 1.0,
+        jitter: false,
+        max_elapsed: Some(Duration::from_secs(15)),
+        ..Default::default()
+    };
+    let res = outer
+        .retry(
+            || {
+                let inner_calls = inner_calls.clone();
+                async move {
+                    assert_eq!(RetryContext::current().unwrap().depth(), 1);
+                    RetryPolicy {
+                        attempts: 5,
+                        base_delay: Duration::from_secs(10),
+                        max_delay: Duration::from_secs(60),
+                        backoff_factor: 1.0,
+                        jitter: false,
+                        ..Default::default()
+                    }
+                    .retry(
+                        || {
+                            inner_calls.fetch_add(1, Ordering::SeqCst);
+                            let ctx = RetryContext::current().unwrap();
+                            assert_eq!(ctx.depth(), 2);
+                            assert!(ctx.remaining().unwrap() <= Duration::from_secs(15));
+                            async { Err::<(), _>("inner down") }
+                        },
+                        |_| true,
+                    )
+                    .await
+                }
+            },
+            |_| true,
+        )
+        .await;
+    assert_eq!(res, Err("inner down"));
+    // attempts at 0s and 10s; a third would start past the 15s deadline
+    assert_eq!(inner_calls.load(Ordering::SeqCst), 2);
+}
+
+#[tokio::test]
+async fn operation_reads_the_remaining_budget() {
+    tokio::time::pause();
+    assert_eq!(remaining_budget(), None);
+    assert_eq!(RetryContext::current(), None);
+
+    let budgets = std::sync::Mutex::new(Vec::new());
+    let _ = RetryPolicy {
+        attempts: 3,
+        base_delay: Duration::from_secs(10),
+        max_delay: Duration::from_secs(60),
+        backoff_factor: 1.0,
+        jitter: false,
+        max_elapsed: Some(Duration::from_secs(60)),
+        ..Default::default()
+    }
+    .retry(
+        || async {
+            budgets.lock().unwrap().push(remaining_budget().unwrap());
+            Err::<(), _>("down")
+        },
+        |_| true,
+    )
+    .await;
+    let budgets = budgets.into_inner().unwrap();
+    assert_eq!(budgets.len(), 3);
+    assert!(budgets[0] <= Duration::from_secs(60) && budgets[0] > Duration::from_secs(59));
+    assert!(budgets[2] <= Duration::from_secs(40) && budgets[2] > Duration::from_secs(39));
+
+    // without any max_elapsed there is no budget
+    let res = RetryPolicy {
+        attempts: 1,
+        base_delay: Duration::from_secs(10),
+        max_delay: Duration::from_secs(60),
+        backoff_factor: 1.0,
+        jitter: false,
+        ..Default::default()
+    }
+    .retry(|| async { Ok::<_, ()>(remaining_budget()) }, |_| true)
+    .await;
+    assert_eq!(res, Ok(None));
+}
+
+static MACRO_CALLS: AtomicUsize = AtomicUsize::new(0);
+
+#[retry(
+    attempts = 10,
+    base_delay_ms = 10000,
+    max_delay_ms = 10000,
+    jitter = false
+)]
+async fn inner_with_macro() -> Result<(), String> {
+    MACRO_CALLS.fetch_add(1, Ordering::SeqCst);
+    Err("inner down".to_string())
+}
+
+#[tokio::test]
+async fn retry_functions_inherit_the_deadline() {
+    tokio::time::pause();
+    let res = RetryPolicy {
+        attempts: 1,
+        base_delay: Duration::from_secs(10),
+        max_delay: Duration::from_secs(60),
+        backoff_factor: 1.0,
+        jitter: false,
+        max_elapsed: Some(Duration::from_secs(35)),
+        ..Default::default()
+    }
+    .retry(inner_with_macro, |_| true)
+    .await;
+    assert_eq!(res, Err("inner down".to_string()));
+    assert_eq!(MACRO_CALLS.load(Ordering::SeqCst), 4);
+}
+
+#[retry(
+    attempts = 10,
+    base_delay_ms = 10000,
+    max_delay_ms = 10000,
+    max_elapsed_ms = 15000,
+    jitter = false
+)]
+async fn with_own_budget() -> Result<(), String> {
+    Err(format!(
+        "{:?}",
+        remaining_budget().map(|d| d.as_secs_f64().round() as u64)
+    ))
+}
+
+#[tokio::test]
+async fn retry_macro_accepts_max_elapsed() {
+    tokio::time::pause();
+    let start = Instant::now();
+    assert_eq!(with_own_budget().await, Err("Some(5)".to_string()));
+    assert!(start.elapsed() < Duration::from_secs(15));
+}
@@ -0,0 +1,189 @@
+use asyn_retry_policy::dead_letter::{
+    ChannelDeadLetterSink, FileDeadLetterSink, MemoryDeadLetterSink, replay,
+};
+use asyn_retry_policy::{DeadLetterError, DeadLetterRetryError, RetryPolicy};
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::Duration;
+
+#[tokio::test]
+async fn exhausted_sequence_is_recorded_with_context() {
+    let sink = MemoryDeadLetterSink::new();
+    let res = RetryPolicy {
+        attempts: 3,
+        base_delay: Duration::from_millis(1),
+        jitter: false,
+        name: Some("webhooks"),
+        ..Default::default()
+    }
+    .retry_or_dead_letter(
+        "order-1".to_string(),
+        |payload: String| async move { Err::<(), _>(format!("{payload} rejected")) },
+        |_| true,
+        &sink,
+    )
+    .await;
+    assert!(matches!(res, Err(DeadLetterRetryError::Recorded(e)) if e == "order-1 rejected"));
+
+    let letters = sink.drain();
+    assert_eq!(letters.len(), 1);
+    let letter = &letters[0];
+    assert_eq!(letter.payload, "order-1");
+    assert_eq!(letter.policy.as_deref(), Some("webhooks"));
+    assert_eq!(letter.attempts, 3);
+    assert_eq!(letter.errors, vec!["order-1 rejected"; 3]);
+    assert!(letter.first_failure <= letter.last_failure);
+}
+
+#[tokio::test]
+async fn successful_sequence_records_nothing() {
+    let sink = MemoryDeadLetterSink::new();
+    let res = RetryPolicy {
+        attempts: 3,
+        base_delay: Duration::from_millis(1),
+        jitter: false,
+        name: Some("webhooks"),
+        ..Default::default()
+    }
+    .retry_or_dead_letter(1u8, |n| async move { Ok::<_, String>(n) }, |_| true, &sink)
+    .await;
+    assert_eq!(res.unwrap(), 1);
+    assert!(sink.is_empty());
+}
+
+#[tokio::test]
+async fn file_sink_round_trips_and_replays() {
+    let path = std::env::temp_dir().join(format!("asyn-retry-dead-{}.jsonl", std::process::id()));
+    let _ = std::fs::remove_file(&path);
+    let sink = FileDeadLetterSink::new(&path);
+    let up = Arc::new(AtomicBool::new(false));
+    let op = {
+        let up = up.clone();
+        move |n: u32| {
+            let up = up.clone();
+            async move {
+                if up.load(Ordering::SeqCst) {
+                    Ok(n)
+                } else {
+                    Err("down")
+                }
+            }
+        }
+    };
+
+    for n in [1, 2] {
+        let _ = RetryPolicy {
+            attempts: 3,
+            base_delay: Duration::from_millis(1),
+            jitter: false,
+            name: Some("webhooks"),
+            ..Default::default()
+        }
+        .retry_or_dead_letter(n, op.clone(), |_| true, &sink)
+        .await;
+    }
+    let letters = sink.read_all().unwrap();
+    assert_eq!(
+        letters.iter().map(|l| l.payload).collect::<Vec<_>>(),
+        vec![1, 2]
+    );
+
+    up.store(true, Ordering::SeqCst);
+    let retry_sink = MemoryDeadLetterSink::new();
+    let report = replay(
+        &RetryPolicy {
+            attempts: 3,
+            base_delay: Duration::from_millis(1),
+            jitter: false,
+            name: Some("webhooks"),
+            ..Default::default()
+        },
+        letters,
+        op,
+        |_| true,
+        &retry_sink,
+    )
+    .await;
+    assert_eq!(report.succeeded, 2);
+    assert_eq!(report.failed, 0);
+    assert!(report.unrecorded.is_empty());
+    let _ = std::fs::remove_file(&path);
+}
+
+#[tokio::test]
+async fn channel_sink_delivers_letters() {
+    let (sink, mut rx) = ChannelDeadLetterSink::channel(4);
+    let _ = RetryPolicy {
+        attempts: 3,
+        base_delay: Duration::from_millis(1),
+        jitter: false,
+        name: Some("webhooks"),
+        ..Default::default()
+    }
+    .retry_or_dead_letter('x', |_| async { Err::<(), _>("nope") }, |_| true, &sink)
+    .await;
+    let letter = rx.recv().await.unwrap();
+    assert_eq!(letter.payload, 'x');
+    assert_eq!(letter.attempts, 3);
+}
+
+#[tokio::test]
+async fn rejected_errors_are_not_dead_lettered() {
+    let sink = MemoryDeadLetterSink::new();
+    let res = RetryPolicy {
+        attempts: 3,
+        base_delay: Duration::from_millis(1),
+        jitter: false,
+        ..Default::default()
+    }
+    .retry_or_dead_letter('x', |_| async { Err::<(), _>("invalid") }, |_| false, &sink)
+    .await;
+    assert!(matches!(res, Err(DeadLetterRetryError::Stopped("invalid"))));
+    assert!(sink.is_empty());
+}
+
+#[tokio::test]
+async fn sink_failures_hand_the_letter_back() {
+    let (sink, rx) = ChannelDeadLetterSink::channel(1);
+    drop(rx);
+    let policy = RetryPolicy {
+        attempts: 2,
+        base_delay: Duration::from_millis(1),
+        jitter: false,
+        name: Some("webhooks"),
+        ..Default::default()
+    };
+    let res = policy
+        .retry_or_dead_letter(7u32, |_| async { Err::<(), _>("down") }, |_| true, &sink)
+        .await;
+    match res {
+        Err(DeadLetterRetryError::NotRecorded {
+            error,
+            letter,
+            source,
+        }) => {
+            assert_eq!(error, "down");
+            assert_eq!(letter.payload, 7);
+            assert_eq!(letter.attempts, 2);
+            assert!(matches!(source, DeadLetterError::Closed));
+        }
+        other => panic!("expected an unrecorded dead letter, got {other:?}"),
+    }
+
+    let letters = MemoryDeadLetterSink::new();
+    let _ = policy
+        .retry_or_dead_letter(8u32, |_| async { Err::<(), _>("down") }, |_| true, &letters)
+        .await;
+    let report = replay(
+        &policy,
+        letters.drain(),
+        |_| async { Err::<(), _>("still down") },
+        |_| true,
+        &sink,
+    )
+    .await;
+    assert_eq!(report.failed, 0);
+    assert_eq!(report.unrecorded.len(), 1);
+    assert_eq!(report.unrecorded[0].payload, 8);
+    assert_eq!(report.unrecorded[0].errors, ["still down"; 2]);
+}
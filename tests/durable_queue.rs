@@ -0,0 +1,180 @@
+use asyn_retry_policy::RetryPolicy;
+use asyn_retry_policy::durable::{DeadJob, DurableRetryQueue};
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+fn paths(name: &str) -> (PathBuf, PathBuf) {
+    let dir =
+        std::env::temp_dir().join(format!("asyn-retry-policy-{}-{}", name, std::process::id()));
+    let _ = std::fs::remove_dir_all(&dir);
+    std::fs::create_dir_all(&dir).unwrap();
+    (dir.join("queue.log"), dir.join("dead.log"))
+}
+
+#[tokio::test]
+async fn jobs_survive_reopen_with_attempt_state() {
+    let (path, dead) = paths("reopen");
+    {
+        let queue = DurableRetryQueue::open(
+            &path,
+            &dead,
+            RetryPolicy {
+                attempts: 2,
+                base_delay: Duration::from_millis(20),
+                jitter: false,
+                ..Default::default()
+            },
+        )
+        .unwrap();
+        queue.enqueue("hook-1".to_string()).unwrap();
+        queue.enqueue("hook-2".to_string()).unwrap();
+        let report = queue
+            .run_due(
+                |payload: String| async move {
+                    if payload == "hook-1" {
+                        Ok(())
+                    } else {
+                        Err("503")
+                    }
+                },
+                |_| true,
+            )
+            .await
+            .unwrap();
+        assert_eq!(report.succeeded, 1);
+        assert_eq!(report.rescheduled, 1);
+    }
+
+    // "restart": the pending job is replayed with its attempt count and due time
+    let queue = DurableRetryQueue::<String>::open(
+        &path,
+        &dead,
+        RetryPolicy {
+            attempts: 2,
+            base_delay: Duration::from_millis(20),
+            jitter: false,
+            ..Default::default()
+        },
+    )
+    .unwrap();
+    let jobs = queue.jobs();
+    assert_eq!(jobs.len(), 1);
+    assert_eq!(jobs[0].payload, "hook-2");
+    assert_eq!(jobs[0].attempts, 1);
+
+    // not due yet
+    let report = queue
+        .run_due(|_| async { Ok::<_, &str>(()) }, |_| true)
+        .await
+        .unwrap();
+    assert_eq!(report.succeeded, 0);
+
+    tokio::time::sleep(Duration::from_millis(30)).await;
+    let report = queue
+        .run_due(|_| async { Ok::<_, &str>(()) }, |_| true)
+        .await
+        .unwrap();
+    assert_eq!(report.succeeded, 1);
+    assert!(queue.is_empty());
+}
+
+#[tokio::test]
+async fn exhausted_jobs_move_to_dead_letter_file() {
+    let (path, dead) = paths("dead");
+    let queue = DurableRetryQueue::open(
+        &path,
+        &dead,
+        RetryPolicy {
+            attempts: 2,
+            base_delay: Duration::from_millis(20),
+            jitter: false,
+            ..Default::default()
+        },
+    )
+    .unwrap();
+    queue.enqueue(7u32).unwrap();
+    queue
+        .run_due(|_| async { Err("boom") }, |_| true)
+        .await
+        .unwrap();
+    tokio::time::sleep(Duration::from_millis(30)).await;
+    let report = queue
+        .run_due(|_| async { Err("boom") }, |_| true)
+        .await
+        .unwrap();
+    assert_eq!(report.dead_lettered, 1);
+    assert!(queue.is_empty());
+
+    let contents = std::fs::read_to_string(&dead).unwrap();
+    let dead_job: DeadJob<u32> = serde_json::from_str(contents.lines().next().unwrap()).unwrap();
+    assert_eq!(dead_job.job.payload, 7);
+    assert_eq!(dead_job.job.attempts, 2);
+    assert_eq!(dead_job.error, "boom");
+
+    // reopening after compaction finds nothing left to do
+    drop(queue);
+    let queue = DurableRetryQueue::<u32>::open(
+        &path,
+        &dead,
+        RetryPolicy {
+            attempts: 2,
+            base_delay: Duration::from_millis(20),
+            jitter: false,
+            ..Default::default()
+        },
+    )
+    .unwrap();
+    assert!(queue.is_empty());
+}
+
+#[tokio::test]
+async fn worker_runs_until_shutdown() {
+    let (path, dead) = paths("worker");
+    let queue = Arc::new(
+        DurableRetryQueue::open(
+            &path,
+            &dead,
+            RetryPolicy {
+                attempts: 2,
+                base_delay: Duration::from_millis(20),
+                jitter: false,
+                ..Default::default()
+            },
+        )
+        .unwrap(),
+    );
+    let delivered = Arc::new(Mutex::new(Vec::new()));
+    let (tx, rx) = tokio::sync::oneshot::channel::<()>();
+
+    let worker = tokio::spawn({
+        let queue = queue.clone();
+        let delivered = delivered.clone();
+        async move {
+            queue
+                .run(
+                    move |n: u32| {
+                        let delivered = delivered.clone();
+                        async move {
+                            delivered.lock().unwrap().push(n);
+                            Ok::<_, &str>(())
+                        }
+                    },
+                    |_| true,
+                    async {
+                        let _ = rx.await;
+                    },
+                )
+                .await
+        }
+    });
+
+    queue.enqueue(1).unwrap();
+    queue.enqueue(2).unwrap();
+    while !queue.is_empty() {
+        tokio::time::sleep(Duration::from_millis(5)).await;
+    }
+    tx.send(()).unwrap();
+    worker.await.unwrap().unwrap();
+    assert_eq!(*delivered.lock().unwrap(), vec![1, 2]);
+}
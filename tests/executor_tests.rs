@@ -0,0 +1,139 @@
+use asyn_retry_policy::{RetryExecutor, RetryPolicy};
+use std::sync::Arc;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::time::Duration;
+
+/// Operation failing `failures` times before returning `value`.
+fn flaky(
+    failures: usize,
+    value: u32,
+) -> impl FnMut() -> std::future::Ready<Result<u32, &'static str>> + Send + 'static {
+    let mut calls = 0;
+    move || {
+        calls += 1;
+        std::future::ready(if calls <= failures {
+            Err("busy")
+        } else {
+            Ok(value)
+        })
+    }
+}
+
+#[tokio::test]
+async fn submissions_use_their_own_policy() {
+    tokio::time::pause();
+    let executor = RetryExecutor::new(2);
+    let a = executor.submit(
+        RetryPolicy {
+            attempts: 3,
+            base_delay: Duration::from_millis(100),
+            jitter: false,
+            ..Default::default()
+        },
+        flaky(2, 1),
+        |_| true,
+    );
+    let b = executor.submit(
+        RetryPolicy {
+            attempts: 2,
+            base_delay: Duration::from_millis(100),
+            jitter: false,
+            ..Default::default()
+        },
+        flaky(2, 2),
+        |_| true,
+    );
+    assert_eq!(a.await, Some(Ok(1)));
+    assert_eq!(b.await, Some(Err("busy")));
+    executor.drain().await;
+    assert_eq!(executor.pending(), 0);
+}
+
+#[tokio::test]
+async fn concurrency_is_bounded() {
+    tokio::time::pause();
+    let executor = RetryExecutor::new(2);
+    let running = Arc::new(AtomicUsize::new(0));
+    let peak = Arc::new(AtomicUsize::new(0));
+
+    let tickets: Vec<_> = (0..6)
+        .map(|i| {
+            let running = running.clone();
+            let peak = peak.clone();
+            executor.submit(
+                RetryPolicy {
+                    attempts: 1,
+                    base_delay: Duration::from_millis(100),
+                    jitter: false,
+                    ..Default::default()
+                },
+                move || {
+                    let running = running.clone();
+                    let peak = peak.clone();
+                    async move {
+                        let now = running.fetch_add(1, Ordering::SeqCst) + 1;
+                        peak.fetch_max(now, Ordering::SeqCst);
+                        tokio::time::sleep(Duration::from_millis(10)).await;
+                        running.fetch_sub(1, Ordering::SeqCst);
+                        Ok::<_, ()>(i)
+                    }
+                },
+                |_| true,
+            )
+        })
+        .collect();
+    for (i, ticket) in tickets.into_iter().enumerate() {
+        assert_eq!(ticket.await, Some(Ok(i)));
+    }
+    assert_eq!(peak.load(Ordering::SeqCst), 2);
+}
+
+#[tokio::test]
+async fn pause_holds_pending_retries() {
+    tokio::time::pause();
+    let executor = RetryExecutor::new(1);
+    let ticket = executor.submit(
+        RetryPolicy {
+            attempts: 3,
+            base_delay: Duration::from_millis(100),
+            jitter: false,
+            ..Default::default()
+        },
+        flaky(1, 7),
+        |_| true,
+    );
+
+    // let the first attempt fail and the retry be scheduled
+    while executor.pending() == 0 || executor.in_flight() > 0 {
+        tokio::time::sleep(Duration::from_millis(1)).await;
+    }
+    executor.pause();
+    assert!(executor.next_due().is_some());
+    tokio::time::sleep(Duration::from_secs(1)).await;
+    assert_eq!(executor.pending(), 1);
+
+    executor.resume();
+    assert_eq!(ticket.await, Some(Ok(7)));
+}
+
+#[tokio::test]
+async fn panicking_attempts_finish_their_bookkeeping() {
+    tokio::time::pause();
+    let executor = RetryExecutor::new(1);
+    let ticket = executor.submit(
+        RetryPolicy {
+            attempts: 3,
+            jitter: false,
+            ..Default::default()
+        },
+        || -> std::future::Ready<Result<u32, ()>> { panic!("attempt panicked") },
+        |_| true,
+    );
+    assert_eq!(ticket.await, None);
+    executor.drain().await;
+    assert_eq!(executor.in_flight(), 0);
+
+    // the worker slot was released as well
+    let ticket = executor.submit(RetryPolicy::default(), flaky(0, 7), |_| true);
+    assert_eq!(ticket.await, Some(Ok(7)));
+}
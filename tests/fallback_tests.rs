@@ -0,0 +1,65 @@
+use asyn_retry_policy::{RetryPolicy, RetryStats};
+use std::sync::Arc;
+use std::sync::atomic::{AtomicU8, Ordering};
+
+#[tokio::test]
+async fn fallback_receives_final_error_and_stats() {
+    let policy = RetryPolicy {
+        jitter: false,
+        ..Default::default()
+    };
+    let tries = Arc::new(AtomicU8::new(0));
+    let res = policy
+        .retry_or_else(
+            {
+                let tries = tries.clone();
+                move || {
+                    let tries = tries.clone();
+                    async move {
+                        tries.fetch_add(1, Ordering::SeqCst);
+                        Err::<u8, _>("down")
+                    }
+                }
+            },
+            |_| true,
+            |e, stats: RetryStats| async move {
+                assert_eq!(e, "down");
+                assert_eq!(stats.attempts, 3);
+                Ok(1u8)
+            },
+        )
+        .await;
+    assert_eq!(res.unwrap(), 1u8);
+    assert_eq!(tries.load(Ordering::SeqCst), 3);
+}
+
+#[tokio::test]
+async fn fallback_not_called_on_success() {
+    let policy = RetryPolicy::default();
+    let res = policy
+        .retry_or_else(
+            || async { Ok::<u8, &str>(2) },
+            |_| true,
+            |_, _| async { panic!("fallback must not run") },
+        )
+        .await;
+    assert_eq!(res.unwrap(), 2u8);
+}
+
+async fn stale_value(_e: &'static str, stats: RetryStats) -> Result<u8, &'static str> {
+    Ok(100 + stats.attempts as u8)
+}
+
+#[asyn_retry_policy::retry(attempts = 2, base_delay_ms = 1, fallback = stale_value)]
+async fn macro_with_fallback(tries: Arc<AtomicU8>) -> Result<u8, &'static str> {
+    tries.fetch_add(1, Ordering::SeqCst);
+    Err("down")
+}
+
+#[tokio::test]
+async fn test_macro_fallback() {
+    let tries = Arc::new(AtomicU8::new(0));
+    let res = macro_with_fallback(tries.clone()).await;
+    assert_eq!(res.unwrap(), 102u8);
+    assert_eq!(tries.load(Ordering::SeqCst), 2);
+}
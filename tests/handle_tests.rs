@@ -0,0 +1,105 @@
+use asyn_retry_policy::RetryPolicy;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::time::Duration;
+
+fn failing_until(
+    ok_after: usize,
+) -> (
+    Arc<AtomicUsize>,
+    impl FnMut() -> std::future::Ready<Result<usize, &'static str>>,
+) {
+    let calls = Arc::new(AtomicUsize::new(0));
+    let counter = calls.clone();
+    let op = move || {
+        let n = counter.fetch_add(1, Ordering::SeqCst) + 1;
+        std::future::ready(if n > ok_after { Ok(n) } else { Err("offline") })
+    };
+    (calls, op)
+}
+
+#[tokio::test]
+async fn reports_progress_and_retry_now_skips_backoff() {
+    tokio::time::pause();
+    let (calls, op) = failing_until(2);
+    let handle = RetryPolicy {
+        attempts: 5,
+        base_delay: Duration::from_secs(12),
+        max_delay: Duration::from_secs(60),
+        jitter: false,
+        ..Default::default()
+    }
+    .spawn(op, |_| true);
+    let mut progress = handle.progress();
+
+    progress.wait_for(|p| p.next_retry.is_some()).await.unwrap();
+    assert_eq!(handle.attempt(), 1);
+    let remaining = handle.next_retry().unwrap() - tokio::time::Instant::now();
+    assert_eq!(remaining, Duration::from_secs(12));
+
+    handle.retry_now();
+    progress.wait_for(|p| p.attempt == 2).await.unwrap();
+    assert_eq!(calls.load(Ordering::SeqCst), 2);
+
+    handle.retry_now();
+    assert_eq!(handle.await.unwrap(), Ok(3));
+}
+
+#[tokio::test]
+async fn abort_stops_the_sequence() {
+    tokio::time::pause();
+    let (calls, op) = failing_until(usize::MAX);
+    let handle = RetryPolicy {
+        attempts: 5,
+        base_delay: Duration::from_secs(12),
+        max_delay: Duration::from_secs(60),
+        jitter: false,
+        ..Default::default()
+    }
+    .spawn(op, |_| true);
+    handle
+        .progress()
+        .wait_for(|p| p.next_retry.is_some())
+        .await
+        .unwrap();
+    handle.abort();
+    assert!(handle.await.unwrap_err().is_cancelled());
+    assert_eq!(calls.load(Ordering::SeqCst), 1);
+}
+
+#[tokio::test]
+async fn finishes_without_intervention() {
+    tokio::time::pause();
+    let (_, op) = failing_until(1);
+    let handle = RetryPolicy {
+        attempts: 5,
+        base_delay: Duration::from_secs(12),
+        max_delay: Duration::from_secs(60),
+        jitter: false,
+        ..Default::default()
+    }
+    .spawn(op, |_| true);
+    assert_eq!(handle.await.unwrap(), Ok(2));
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+async fn retry_now_right_after_backoff_starts_is_not_lost() {
+    for _ in 0..200 {
+        let (_, op) = failing_until(1);
+        let handle = RetryPolicy {
+            attempts: 2,
+            base_delay: Duration::from_secs(3600),
+            jitter: false,
+            ..Default::default()
+        }
+        .spawn(op, |_| true);
+        handle
+            .progress()
+            .wait_for(|p| p.next_retry.is_some())
+            .await
+            .unwrap();
+        handle.retry_now();
+        let res = tokio::time::timeout(Duration::from_secs(5), handle).await;
+        assert_eq!(res.expect("retry_now was lost").unwrap(), Ok(2));
+    }
+}
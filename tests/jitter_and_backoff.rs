@@ -10,11 +10,13 @@ use std::time::Duration;
 async fn deterministic_jitter_is_reproducible() {
     tokio::time::pause();
 
-    let mut policy = RetryPolicy::default();
-    policy.jitter = true;
-    policy.rng_seed = Some(42);
-    policy.base_delay = Duration::from_millis(100);
-    policy.max_delay = Duration::from_millis(1000);
+    let policy = RetryPolicy {
+        jitter: true,
+        rng_seed: Some(42),
+        base_delay: Duration::from_millis(100),
+        max_delay: Duration::from_millis(1000),
+        ..Default::default()
+    };
 
     let tries = Arc::new(AtomicU8::new(0));
     let t = tries.clone();
@@ -57,11 +59,13 @@ async fn deterministic_jitter_is_reproducible() {
 async fn max_delay_is_enforced() {
     tokio::time::pause();
 
-    let mut policy = RetryPolicy::default();
-    policy.jitter = false;
-    policy.base_delay = Duration::from_secs(1);
-    policy.backoff_factor = 10.0; // big multiplier
-    policy.max_delay = Duration::from_millis(1500); // 1.5s max
+    let policy = RetryPolicy {
+        jitter: false,
+        base_delay: Duration::from_secs(1),
+        backoff_factor: 10.0,                  // big multiplier
+        max_delay: Duration::from_millis(1500), // 1.5s max
+        ..Default::default()
+    };
 
     let tries = Arc::new(AtomicU8::new(0));
     let t = tries.clone();
@@ -93,10 +97,12 @@ async fn max_delay_is_enforced() {
 
 #[test]
 fn compute_backoff_values() {
-    let mut policy = RetryPolicy::default();
-    policy.base_delay = Duration::from_millis(100);
-    policy.backoff_factor = 2.0;
-    policy.max_delay = Duration::from_secs(1);
+    let policy = RetryPolicy {
+        base_delay: Duration::from_millis(100),
+        backoff_factor: 2.0,
+        max_delay: Duration::from_secs(1),
+        ..Default::default()
+    };
 
     // attempt 1 -> 100ms
     assert_eq!(policy.compute_backoff(1), Duration::from_millis(100));
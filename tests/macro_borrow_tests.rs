@@ -0,0 +1,171 @@
+use asyn_retry_policy::retry;
+
+#[derive(Default)]
+struct Connection {
+    attempts: u32,
+    log: Vec<String>,
+}
+
+impl Connection {
+    #[retry(attempts = 5)]
+    async fn send(&mut self, payload: &str) -> Result<u32, String> {
+        self.attempts += 1;
+        self.log.push(format!("{payload} #{}", self.attempts));
+        if self.attempts < 3 {
+            Err(format!("refused on attempt {}", self.attempts))
+        } else {
+            Ok(self.attempts)
+        }
+    }
+
+    #[retry(attempts = 2)]
+    async fn peek(&self, seen: &mut Vec<u32>) -> Result<u32, String> {
+        seen.push(self.attempts);
+        if seen.len() < 2 {
+            Err("busy".into())
+        } else {
+            Ok(self.attempts)
+        }
+    }
+}
+
+#[tokio::test(start_paused = true)]
+async fn mut_self_method_is_reborrowed_on_every_attempt() {
+    let mut conn = Connection::default();
+    assert_eq!(conn.send("ping").await, Ok(3));
+    assert_eq!(conn.attempts, 3);
+    assert_eq!(conn.log, ["ping #1", "ping #2", "ping #3"]);
+}
+
+#[tokio::test(start_paused = true)]
+async fn shared_self_and_mut_argument() {
+    let conn = Connection {
+        attempts: 7,
+        ..Default::default()
+    };
+    let mut seen = Vec::new();
+    assert_eq!(conn.peek(&mut seen).await, Ok(7));
+    assert_eq!(seen, [7, 7]);
+}
+
+#[tokio::test(start_paused = true)]
+async fn borrowing_methods_can_be_spawned() {
+    let handle = tokio::spawn(async {
+        let mut conn = Connection::default();
+        conn.send("spawned").await.map(|_| conn.log.len())
+    });
+    assert_eq!(handle.await.unwrap(), Ok(3));
+}
+
+/// Not `Clone`: only usable in `#[retry]` when borrowed.
+struct Ledger {
+    entries: Vec<u32>,
+}
+
+#[retry(attempts = 3)]
+async fn append(#[retry(borrow)] ledger: Ledger, value: u32) -> Result<usize, String> {
+    ledger.entries.push(value);
+    if ledger.entries.len() < 2 {
+        Err("conflict".into())
+    } else {
+        Ok(ledger.entries.len())
+    }
+}
+
+#[tokio::test(start_paused = true)]
+async fn owned_argument_can_be_borrowed() {
+    let ledger = Ledger {
+        entries: Vec::new(),
+    };
+    assert_eq!(append(ledger, 4).await, Ok(2));
+}
+
+#[retry(attempts = 3)]
+async fn fresh_copy(mut buf: Vec<u8>, total: &mut usize) -> Result<usize, usize> {
+    buf.push(1);
+    *total += 1;
+    if *total < 3 {
+        Err(buf.len())
+    } else {
+        Ok(buf.len())
+    }
+}
+
+#[tokio::test(start_paused = true)]
+async fn owned_arguments_are_cloned_per_attempt() {
+    let mut total = 0;
+    // every attempt starts from the caller's (empty) buffer
+    assert_eq!(fresh_copy(Vec::new(), &mut total).await, Ok(1));
+    assert_eq!(total, 3);
+}
+
+#[derive(Clone)]
+struct Job {
+    name: &'static str,
+    runs: std::sync::Arc<std::sync::atomic::AtomicU32>,
+}
+
+impl Job {
+    #[retry(attempts = 3)]
+    async fn run(self) -> Result<&'static str, String> {
+        let runs = self.runs.fetch_add(1, std::sync::atomic::Ordering::SeqCst);
+        if runs == 0 {
+            Err(format!("{} failed", self.name))
+        } else {
+            Ok(self.into_name())
+        }
+    }
+
+    fn into_name(self) -> &'static str {
+        self.name
+    }
+}
+
+#[tokio::test(start_paused = true)]
+async fn owned_self_is_cloned_per_attempt() {
+    let job = Job {
+        name: "reindex",
+        runs: Default::default(),
+    };
+    assert_eq!(job.clone().run().await, Ok("reindex"));
+    assert_eq!(job.runs.load(std::sync::atomic::Ordering::SeqCst), 2);
+}
+
+// names whose hidden bindings used to collide
+#[allow(clippy::duplicate_underscore_argument)]
+#[retry(attempts = 3)]
+async fn similar_names(a: &mut Vec<u32>, _a: &mut Vec<u32>) -> Result<(), String> {
+    a.push(1);
+    _a.push(2);
+    if a.len() < 2 {
+        Err("retry".into())
+    } else {
+        Ok(())
+    }
+}
+
+impl Connection {
+    #[allow(clippy::duplicate_underscore_argument)]
+    #[retry(attempts = 3)]
+    async fn count(&mut self, _self: &mut u32) -> Result<u32, String> {
+        self.attempts += 1;
+        *_self += 10;
+        if self.attempts < 2 {
+            Err("retry".into())
+        } else {
+            Ok(*_self)
+        }
+    }
+}
+
+#[tokio::test(start_paused = true)]
+async fn arguments_with_similar_names_stay_apart() {
+    let (mut a, mut b) = (Vec::new(), Vec::new());
+    assert_eq!(similar_names(&mut a, &mut b).await, Ok(()));
+    assert_eq!((a, b), (vec![1, 1], vec![2, 2]));
+
+    let mut conn = Connection::default();
+    let mut total = 0;
+    assert_eq!(conn.count(&mut total).await, Ok(20));
+    assert_eq!(conn.attempts, 2);
+}
@@ -0,0 +1,179 @@
+use asyn_retry_policy::retry;
+use async_trait::async_trait;
+use std::sync::atomic::{AtomicU32, Ordering};
+
+/// Fails the first `failures` calls.
+#[derive(Default)]
+struct Flaky {
+    failures: u32,
+    calls: AtomicU32,
+}
+
+impl Flaky {
+    fn new(failures: u32) -> Self {
+        Flaky {
+            failures,
+            calls: AtomicU32::new(0),
+        }
+    }
+
+    fn call(&self) -> Result<u32, String> {
+        let call = self.calls.fetch_add(1, Ordering::SeqCst) + 1;
+        if call <= self.failures {
+            Err(format!("failure #{call}"))
+        } else {
+            Ok(call)
+        }
+    }
+
+    fn calls(&self) -> u32 {
+        self.calls.load(Ordering::SeqCst)
+    }
+}
+
+#[retry(attempts = 4)]
+impl Flaky {
+    async fn fetch(&self) -> Result<u32, String> {
+        self.call()
+    }
+
+    #[retry(attempts = 2)]
+    async fn fetch_twice(&self) -> Result<u32, String> {
+        self.call()
+    }
+
+    #[retry(skip)]
+    async fn fetch_once(&self) -> Result<u32, String> {
+        self.call()
+    }
+
+    async fn reset(&mut self) -> Result<(), String> {
+        self.calls = AtomicU32::new(0);
+        Ok(())
+    }
+}
+
+#[tokio::test(start_paused = true)]
+async fn impl_attribute_applies_to_every_async_method() {
+    let flaky = Flaky::new(3);
+    assert_eq!(flaky.fetch().await, Ok(4));
+    assert_eq!(flaky.calls(), 4);
+}
+
+#[tokio::test(start_paused = true)]
+async fn methods_override_and_skip() {
+    let mut flaky = Flaky::new(3);
+    assert_eq!(flaky.fetch_twice().await, Err("failure #2".to_string()));
+    assert_eq!(flaky.fetch_once().await, Err("failure #3".to_string()));
+    flaky.reset().await.unwrap();
+    assert_eq!(flaky.calls(), 0);
+}
+
+#[async_trait]
+trait Store: Send + Sync {
+    async fn load(&self, key: &str) -> Result<String, String>;
+    async fn save(&mut self, key: &str, value: String) -> Result<(), String>;
+}
+
+#[derive(Default)]
+struct Remote {
+    flaky: Flaky,
+    saved: Vec<String>,
+}
+
+// `#[async_trait]` expands first
+#[async_trait]
+#[retry(attempts = 3)]
+impl Store for Remote {
+    async fn load(&self, key: &str) -> Result<String, String> {
+        self.flaky.call().map(|call| format!("{key}@{call}"))
+    }
+
+    #[retry(attempts = 5)]
+    async fn save(&mut self, key: &str, value: String) -> Result<(), String> {
+        self.saved.push(format!("{key}={value}"));
+        self.flaky.call().map(drop)
+    }
+}
+
+#[derive(Default)]
+struct Replica {
+    flaky: Flaky,
+    saved: Vec<String>,
+}
+
+// `#[retry]` expands first
+#[retry(attempts = 3)]
+#[async_trait]
+impl Store for Replica {
+    async fn load(&self, key: &str) -> Result<String, String> {
+        self.flaky.call().map(|call| format!("{key}@{call}"))
+    }
+
+    #[retry(skip)]
+    async fn save(&mut self, key: &str, value: String) -> Result<(), String> {
+        self.saved.push(format!("{key}={value}"));
+        self.flaky.call().map(drop)
+    }
+}
+
+#[tokio::test(start_paused = true)]
+async fn async_trait_expanded_first() {
+    let mut remote = Remote {
+        flaky: Flaky::new(2),
+        ..Default::default()
+    };
+    assert_eq!(remote.load("a").await, Ok("a@3".to_string()));
+    remote.flaky = Flaky::new(3);
+    remote.save("b", "1".to_string()).await.unwrap();
+    assert_eq!(remote.saved, ["b=1"; 4]);
+}
+
+#[tokio::test(start_paused = true)]
+async fn retry_expanded_first() {
+    let mut replica = Replica {
+        flaky: Flaky::new(2),
+        ..Default::default()
+    };
+    assert_eq!(replica.load("a").await, Ok("a@3".to_string()));
+    replica.flaky = Flaky::new(1);
+    assert_eq!(
+        replica.save("b", "1".to_string()).await,
+        Err("failure #1".to_string())
+    );
+    assert_eq!(replica.saved, ["b=1"]);
+}
+
+#[tokio::test(start_paused = true)]
+async fn trait_objects_stay_send() {
+    let mut store: Box<dyn Store> = Box::new(Remote {
+        flaky: Flaky::new(1),
+        ..Default::default()
+    });
+    let saved = tokio::spawn(async move {
+        store.save("k", "v".to_string()).await?;
+        store.load("k").await
+    });
+    assert_eq!(saved.await.unwrap(), Ok("k@3".to_string()));
+}
+
+struct Plain(Flaky);
+
+#[async_trait]
+trait Fetch {
+    async fn fetch(&self) -> Result<u32, String>;
+}
+
+#[async_trait]
+impl Fetch for Plain {
+    #[retry(attempts = 3)]
+    async fn fetch(&self) -> Result<u32, String> {
+        self.0.call()
+    }
+}
+
+#[tokio::test(start_paused = true)]
+async fn single_method_of_async_trait_impl() {
+    let plain = Plain(Flaky::new(2));
+    assert_eq!(plain.fetch().await, Ok(3));
+}
@@ -0,0 +1,105 @@
+use asyn_retry_policy::{RetryPolicy, retry};
+use std::sync::LazyLock;
+use std::sync::atomic::{AtomicU32, Ordering};
+use std::time::Duration;
+use tokio::time::Instant;
+
+const DB_POLICY: RetryPolicy = RetryPolicy {
+    attempts: 4,
+    base_delay: Duration::from_secs(1),
+    jitter: false,
+    ..RetryPolicy::DEFAULT
+};
+
+static QUEUE_POLICY: LazyLock<RetryPolicy> = LazyLock::new(|| RetryPolicy {
+    attempts: 2,
+    jitter: false,
+    ..Default::default()
+});
+
+mod policies {
+    use asyn_retry_policy::RetryPolicy;
+
+    pub fn http() -> RetryPolicy {
+        RetryPolicy {
+            attempts: 5,
+            jitter: false,
+            ..Default::default()
+        }
+    }
+}
+
+/// Always fails, counting its calls.
+fn fail(calls: &AtomicU32) -> Result<(), u32> {
+    Err(calls.fetch_add(1, Ordering::SeqCst) + 1)
+}
+
+#[retry(policy = DB_POLICY)]
+async fn db_query(calls: &AtomicU32) -> Result<(), u32> {
+    fail(calls)
+}
+
+#[retry(policy = DB_POLICY, attempts = 2, base_delay_ms = 10)]
+async fn db_query_overridden(calls: &AtomicU32) -> Result<(), u32> {
+    fail(calls)
+}
+
+#[retry(policy = QUEUE_POLICY)]
+async fn enqueue(calls: &AtomicU32) -> Result<(), u32> {
+    fail(calls)
+}
+
+#[retry(policy = crate::policies::http())]
+async fn http_get(calls: &AtomicU32) -> Result<(), u32> {
+    fail(calls)
+}
+
+#[tokio::test(start_paused = true)]
+async fn const_policy() {
+    let calls = AtomicU32::new(0);
+    let start = Instant::now();
+    assert_eq!(db_query(&calls).await, Err(4));
+    // 1s + 2s + 4s of backoff from the named policy
+    assert_eq!(start.elapsed().as_secs(), 7);
+}
+
+#[tokio::test(start_paused = true)]
+async fn site_options_override_the_policy() {
+    let calls = AtomicU32::new(0);
+    let start = Instant::now();
+    assert_eq!(db_query_overridden(&calls).await, Err(2));
+    assert!(start.elapsed() < Duration::from_secs(1));
+}
+
+#[tokio::test(start_paused = true)]
+async fn static_and_function_policies() {
+    let calls = AtomicU32::new(0);
+    assert_eq!(enqueue(&calls).await, Err(2));
+    let calls = AtomicU32::new(0);
+    assert_eq!(http_get(&calls).await, Err(5));
+}
+
+struct Repo {
+    calls: AtomicU32,
+}
+
+#[retry(policy = DB_POLICY, base_delay_ms = 1)]
+impl Repo {
+    async fn load(&self) -> Result<(), u32> {
+        fail(&self.calls)
+    }
+
+    #[retry(attempts = 1)]
+    async fn load_once(&self) -> Result<(), u32> {
+        fail(&self.calls)
+    }
+}
+
+#[tokio::test(start_paused = true)]
+async fn impl_policy_with_method_overrides() {
+    let repo = Repo {
+        calls: AtomicU32::new(0),
+    };
+    assert_eq!(repo.load().await, Err(4));
+    assert_eq!(repo.load_once().await, Err(5));
+}
@@ -0,0 +1,103 @@
+use asyn_retry_policy::{NestedRetries, NestedRetry, RetryPolicy, retry, set_nested_retry_handler};
+use std::sync::Mutex;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::time::Duration;
+
+/// Run a 3-attempt sequence around an inner sequence using `inner`; returns the backend calls.
+async fn nested(inner: NestedRetries) -> usize {
+    let calls = AtomicUsize::new(0);
+    let _ = RetryPolicy {
+        attempts: 3,
+        base_delay: Duration::from_millis(10),
+        jitter: false,
+        on_nested: NestedRetries::Allow,
+        ..Default::default()
+    }
+    .retry(
+        || async {
+            RetryPolicy {
+                attempts: 3,
+                base_delay: Duration::from_millis(10),
+                jitter: false,
+                on_nested: inner,
+                ..Default::default()
+            }
+            .retry(
+                || {
+                    calls.fetch_add(1, Ordering::SeqCst);
+                    async { Err::<(), _>("down") }
+                },
+                |_| true,
+            )
+            .await
+        },
+        |_| true,
+    )
+    .await;
+    calls.into_inner()
+}
+
+static REPORTS: Mutex<Vec<NestedRetry>> = Mutex::new(Vec::new());
+
+#[tokio::test]
+async fn warns_once_per_pair_of_call_sites() {
+    tokio::time::pause();
+    set_nested_retry_handler(|nested| REPORTS.lock().unwrap().push(*nested));
+
+    assert_eq!(nested(NestedRetries::Warn).await, 9);
+    assert_eq!(nested(NestedRetries::Warn).await, 9);
+
+    let reports = REPORTS.lock().unwrap();
+    assert_eq!(reports.len(), 1);
+    let report = reports[0];
+    assert!(report.outer.file().ends_with("nesting_tests.rs"));
+    assert!(report.inner.file().ends_with("nesting_tests.rs"));
+    assert!(report.inner.line() > report.outer.line());
+    assert_eq!(report.depth, 2);
+    assert_eq!(report.total_attempts, 9);
+    assert!(report.to_string().contains("up to 9 attempts"));
+}
+
+#[tokio::test]
+async fn nested_sequences_are_allowed_by_default() {
+    tokio::time::pause();
+    assert_eq!(RetryPolicy::default().on_nested, NestedRetries::Allow);
+    assert_eq!(RetryPolicy::DEFAULT.on_nested, NestedRetries::Allow);
+    assert_eq!(nested(NestedRetries::default()).await, 9);
+}
+
+#[tokio::test]
+async fn disable_makes_inner_sequences_attempt_once() {
+    tokio::time::pause();
+    assert_eq!(nested(NestedRetries::Allow).await, 9);
+    assert_eq!(nested(NestedRetries::Disable).await, 3);
+}
+
+#[tokio::test]
+async fn cap_limits_attempts_across_layers() {
+    tokio::time::pause();
+    assert_eq!(nested(NestedRetries::CapTotal(6)).await, 6);
+    assert_eq!(nested(NestedRetries::CapTotal(100)).await, 9);
+    // the inner layer always makes at least one attempt
+    assert_eq!(nested(NestedRetries::CapTotal(1)).await, 3);
+}
+
+static BACKEND_CALLS: AtomicUsize = AtomicUsize::new(0);
+
+#[retry(attempts = 3, base_delay_ms = 10, on_nested = "disable")]
+async fn fetch() -> Result<(), String> {
+    BACKEND_CALLS.fetch_add(1, Ordering::SeqCst);
+    Err("down".to_string())
+}
+
+#[retry(attempts = 3, base_delay_ms = 10)]
+async fn handler() -> Result<(), String> {
+    fetch().await
+}
+
+#[tokio::test]
+async fn retry_macro_accepts_on_nested() {
+    tokio::time::pause();
+    assert!(handler().await.is_err());
+    assert_eq!(BACKEND_CALLS.load(Ordering::SeqCst), 3);
+}
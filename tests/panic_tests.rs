@@ -0,0 +1,94 @@
+use asyn_retry_policy::{AttemptError, RetryPolicy};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::time::Duration;
+
+#[tokio::test]
+async fn panics_are_retryable_failures() {
+    let calls = AtomicUsize::new(0);
+    let res = RetryPolicy {
+        attempts: 3,
+        base_delay: Duration::from_millis(1),
+        jitter: false,
+        ..Default::default()
+    }
+    .retry_catch_unwind(
+        || {
+            let n = calls.fetch_add(1, Ordering::SeqCst) + 1;
+            async move {
+                if n < 3 {
+                    panic!("plugin crashed on call {n}");
+                }
+                Ok::<_, String>(n)
+            }
+        },
+        |e| e.is_panic(),
+    )
+    .await;
+    assert_eq!(res, Ok(3));
+}
+
+#[tokio::test]
+async fn final_panic_carries_the_message() {
+    let res = RetryPolicy {
+        attempts: 3,
+        base_delay: Duration::from_millis(1),
+        jitter: false,
+        ..Default::default()
+    }
+    .retry_catch_unwind(
+        || async { panic!("static message") as Result<(), String> },
+        |_| true,
+    )
+    .await;
+    let err = res.unwrap_err();
+    assert_eq!(
+        err,
+        AttemptError::Panicked {
+            message: "static message".to_string()
+        }
+    );
+    assert_eq!(err.to_string(), "attempt panicked: static message");
+    assert_eq!(err.into_failed(), None);
+}
+
+#[tokio::test]
+async fn panics_while_creating_the_future_are_caught() {
+    let calls = AtomicUsize::new(0);
+    let res = RetryPolicy {
+        attempts: 3,
+        base_delay: Duration::from_millis(1),
+        jitter: false,
+        ..Default::default()
+    }
+    .retry_catch_unwind(
+        || -> std::future::Ready<Result<(), String>> {
+            calls.fetch_add(1, Ordering::SeqCst);
+            panic!("no future")
+        },
+        |_| true,
+    )
+    .await;
+    assert!(res.unwrap_err().is_panic());
+    assert_eq!(calls.load(Ordering::SeqCst), 3);
+}
+
+#[tokio::test]
+async fn predicate_distinguishes_errors_from_panics() {
+    let calls = AtomicUsize::new(0);
+    let res = RetryPolicy {
+        attempts: 3,
+        base_delay: Duration::from_millis(1),
+        jitter: false,
+        ..Default::default()
+    }
+    .retry_catch_unwind(
+        || {
+            calls.fetch_add(1, Ordering::SeqCst);
+            async { Err::<(), _>("fatal") }
+        },
+        |e| e.is_panic(),
+    )
+    .await;
+    assert_eq!(res, Err(AttemptError::Failed("fatal")));
+    assert_eq!(calls.load(Ordering::SeqCst), 1);
+}
@@ -0,0 +1,164 @@
+use asyn_retry_policy::{PollError, RetryPolicy, poll_until};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::time::Duration;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Status {
+    Pending,
+    Running,
+    Done,
+}
+
+#[tokio::test]
+async fn polls_until_the_value_is_ready() {
+    tokio::time::pause();
+    let polls = AtomicUsize::new(0);
+    let statuses = [Status::Pending, Status::Running, Status::Done];
+    let res = poll_until(
+        &RetryPolicy {
+            attempts: 5,
+            base_delay: Duration::from_secs(1),
+            jitter: false,
+            ..Default::default()
+        },
+        || {
+            let n = polls.fetch_add(1, Ordering::SeqCst);
+            async move { Ok::<_, String>(statuses[n]) }
+        },
+        |status| *status == Status::Done,
+    )
+    .await;
+    assert_eq!(res, Ok(Status::Done));
+    assert_eq!(polls.load(Ordering::SeqCst), 3);
+}
+
+#[tokio::test]
+async fn exhausted_polling_returns_the_last_value() {
+    tokio::time::pause();
+    let res = poll_until(
+        &RetryPolicy {
+            attempts: 3,
+            base_delay: Duration::from_secs(1),
+            jitter: false,
+            ..Default::default()
+        },
+        || async { Ok::<_, String>(Status::Running) },
+        |status| *status == Status::Done,
+    )
+    .await;
+    assert_eq!(res, Err(PollError::NotReady(Status::Running)));
+}
+
+#[tokio::test]
+async fn polling_stops_on_errors() {
+    tokio::time::pause();
+    let polls = AtomicUsize::new(0);
+    let res = poll_until(
+        &RetryPolicy {
+            attempts: 3,
+            base_delay: Duration::from_secs(1),
+            jitter: false,
+            ..Default::default()
+        },
+        || {
+            polls.fetch_add(1, Ordering::SeqCst);
+            async { Err::<Status, _>("job lost") }
+        },
+        |status| *status == Status::Done,
+    )
+    .await;
+    assert_eq!(res, Err(PollError::Failed("job lost")));
+    assert_eq!(polls.load(Ordering::SeqCst), 1);
+}
+
+#[tokio::test]
+async fn polling_respects_max_elapsed() {
+    tokio::time::pause();
+    let polls = AtomicUsize::new(0);
+    let res = poll_until(
+        &RetryPolicy {
+            attempts: 100,
+            base_delay: Duration::from_secs(1),
+            backoff_factor: 1.0,
+            jitter: false,
+            max_elapsed: Some(Duration::from_millis(3500)),
+            ..Default::default()
+        },
+        || {
+            polls.fetch_add(1, Ordering::SeqCst);
+            async { Ok::<_, String>(Status::Pending) }
+        },
+        |status| *status == Status::Done,
+    )
+    .await;
+    assert!(matches!(res, Err(PollError::NotReady(Status::Pending))));
+    assert_eq!(polls.load(Ordering::SeqCst), 4);
+}
+
+#[tokio::test]
+async fn retry_if_result_decides_on_both_outcomes() {
+    tokio::time::pause();
+    let calls = AtomicUsize::new(0);
+    // empty reads and timeouts are retried; any other error is final
+    let res = RetryPolicy {
+        attempts: 5,
+        base_delay: Duration::from_secs(1),
+        jitter: false,
+        ..Default::default()
+    }
+    .retry_if_result(
+        || {
+            let n = calls.fetch_add(1, Ordering::SeqCst);
+            async move {
+                match n {
+                    0 => Ok(Vec::new()),
+                    1 => Err("timeout"),
+                    _ => Ok(vec![1, 2]),
+                }
+            }
+        },
+        |res| matches!(res, Ok(v) if v.is_empty()) || *res == Err("timeout"),
+    )
+    .await;
+    assert_eq!(res, Ok(vec![1, 2]));
+    assert_eq!(calls.load(Ordering::SeqCst), 3);
+
+    let res = RetryPolicy {
+        attempts: 3,
+        base_delay: Duration::from_secs(1),
+        jitter: false,
+        ..Default::default()
+    }
+    .retry_if_result(|| async { Ok::<u8, &str>(0) }, |res| *res == Ok(0))
+    .await;
+    assert_eq!(res, Ok(0));
+
+    let res = RetryPolicy {
+        attempts: 3,
+        base_delay: Duration::from_secs(1),
+        jitter: false,
+        ..Default::default()
+    }
+    .retry_if_result(|| async { Err::<u8, _>("fatal") }, |res| res.is_ok())
+    .await;
+    assert_eq!(res, Err("fatal"));
+}
+
+#[tokio::test]
+async fn polling_can_run_on_a_spawned_task() {
+    tokio::time::pause();
+    let task = tokio::spawn(async {
+        poll_until(
+            &RetryPolicy {
+                attempts: 3,
+                base_delay: Duration::from_secs(1),
+                jitter: false,
+                ..Default::default()
+            },
+            || async { Ok::<_, String>(Status::Done) },
+            |status| *status == Status::Done,
+        )
+        .await
+    });
+    assert_eq!(task.await.unwrap(), Ok(Status::Done));
+}
@@ -0,0 +1,140 @@
+use asyn_retry_policy::predicate::{always, never, on_error_type, when};
+use asyn_retry_policy::{RetryPolicy, RetryPredicate, retry, retry_matches};
+use std::error::Error;
+use std::fmt;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::time::Duration;
+
+#[derive(Debug, Clone, PartialEq)]
+enum ApiError {
+    Timeout,
+    RateLimited,
+    Status(u16),
+}
+
+fn transient() -> impl RetryPredicate<ApiError> + Clone {
+    retry_matches!(ApiError::Timeout | ApiError::Status(500..=599))
+        .or(retry_matches!(ApiError::RateLimited).max_times(1))
+}
+
+#[test]
+fn combinators_compose() {
+    let mut p = transient();
+    assert!(p.should_retry(&ApiError::Timeout));
+    assert!(p.should_retry(&ApiError::Status(503)));
+    assert!(!p.should_retry(&ApiError::Status(404)));
+    assert!(p.should_retry(&ApiError::RateLimited));
+    assert!(!p.should_retry(&ApiError::RateLimited));
+
+    let mut not_found = retry_matches!(ApiError::Status(404)).not();
+    assert!(not_found.should_retry(&ApiError::Timeout));
+    assert!(!not_found.should_retry(&ApiError::Status(404)));
+
+    let mut server_only = transient().and(when(|e: &ApiError| *e != ApiError::Timeout));
+    assert!(server_only.should_retry(&ApiError::Status(500)));
+    assert!(!server_only.should_retry(&ApiError::Timeout));
+
+    assert!(RetryPredicate::<ApiError>::should_retry(
+        &mut always(),
+        &ApiError::Status(400)
+    ));
+    assert!(!RetryPredicate::<ApiError>::should_retry(
+        &mut never(),
+        &ApiError::Timeout
+    ));
+}
+
+#[test]
+fn guards_are_supported() {
+    let mut p = retry_matches!(ApiError::Status(code) if *code >= 500).into_fn();
+    assert!(p(&ApiError::Status(502)));
+    assert!(!p(&ApiError::Status(400)));
+}
+
+#[derive(Debug)]
+struct Timeout;
+
+impl fmt::Display for Timeout {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("timed out")
+    }
+}
+
+impl Error for Timeout {}
+
+#[derive(Debug)]
+struct Wrapped(Timeout);
+
+impl fmt::Display for Wrapped {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("request failed")
+    }
+}
+
+impl Error for Wrapped {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        Some(&self.0)
+    }
+}
+
+#[test]
+fn on_error_type_searches_the_source_chain() {
+    let mut p = on_error_type::<Timeout>();
+    let direct: Box<dyn Error + Send + Sync> = Box::new(Timeout);
+    let wrapped: Box<dyn Error + Send + Sync> = Box::new(Wrapped(Timeout));
+    let other: Box<dyn Error + Send + Sync> = "boom".into();
+    assert!(p.should_retry(&direct));
+    assert!(p.should_retry(&wrapped));
+    assert!(!p.should_retry(&other));
+
+    let local: Box<dyn Error> = Box::new(Wrapped(Timeout));
+    assert!(p.should_retry(&local));
+}
+
+#[tokio::test]
+async fn composed_predicates_drive_retry() {
+    tokio::time::pause();
+    let calls = AtomicUsize::new(0);
+    let res = RetryPolicy {
+        attempts: 5,
+        base_delay: Duration::from_millis(1),
+        jitter: false,
+        ..Default::default()
+    }
+    .retry(
+        || {
+            calls.fetch_add(1, Ordering::SeqCst);
+            async { Err::<(), _>(ApiError::RateLimited) }
+        },
+        transient().into_fn(),
+    )
+    .await;
+    assert_eq!(res, Err(ApiError::RateLimited));
+    // rate limiting is retried once
+    assert_eq!(calls.load(Ordering::SeqCst), 2);
+}
+
+static MACRO_CALLS: AtomicUsize = AtomicUsize::new(0);
+
+#[retry(attempts = 5, base_delay_ms = 1, predicate = transient())]
+async fn call_api() -> Result<(), ApiError> {
+    let n = MACRO_CALLS.fetch_add(1, Ordering::SeqCst);
+    Err(if n == 0 {
+        ApiError::Timeout
+    } else {
+        ApiError::Status(404)
+    })
+}
+
+#[retry(attempts = 5, base_delay_ms = 1, predicate = retry_matches!(ApiError::Timeout))]
+async fn call_api_timeouts_only() -> Result<(), ApiError> {
+    Err(ApiError::Timeout)
+}
+
+#[tokio::test]
+async fn retry_macro_accepts_predicate_expressions() {
+    tokio::time::pause();
+    assert_eq!(call_api().await, Err(ApiError::Status(404)));
+    assert_eq!(MACRO_CALLS.load(Ordering::SeqCst), 2);
+    assert_eq!(call_api_timeouts_only().await, Err(ApiError::Timeout));
+}
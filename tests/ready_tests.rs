@@ -0,0 +1,81 @@
+use asyn_retry_policy::{ReadyGate, RetryPolicy};
+use std::sync::Arc;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::time::Duration;
+use tokio::sync::{Notify, watch};
+use tokio::time::Instant;
+
+fn fail_once() -> impl FnMut() -> std::future::Ready<Result<u8, &'static str>> {
+    let calls = Arc::new(AtomicUsize::new(0));
+    move || {
+        let n = calls.fetch_add(1, Ordering::SeqCst);
+        std::future::ready(if n == 0 { Err("offline") } else { Ok(1) })
+    }
+}
+
+#[tokio::test]
+async fn notify_cuts_the_backoff_short() {
+    tokio::time::pause();
+    let online = Arc::new(Notify::new());
+    let start = Instant::now();
+    let signal = online.clone();
+    tokio::spawn(async move {
+        tokio::time::sleep(Duration::from_secs(2)).await;
+        signal.notify_one();
+    });
+    let res = RetryPolicy {
+        attempts: 3,
+        base_delay: Duration::from_secs(30),
+        max_delay: Duration::from_secs(60),
+        jitter: false,
+        ..Default::default()
+    }
+    .retry_when_ready(fail_once(), |_| true, online)
+    .await;
+    assert_eq!(res, Ok(1));
+    assert!(start.elapsed() < Duration::from_secs(3));
+}
+
+#[tokio::test]
+async fn backoff_is_the_upper_bound() {
+    tokio::time::pause();
+    let online = Notify::new();
+    let start = Instant::now();
+    let res = RetryPolicy {
+        attempts: 3,
+        base_delay: Duration::from_secs(30),
+        max_delay: Duration::from_secs(60),
+        jitter: false,
+        ..Default::default()
+    }
+    .retry_when_ready(fail_once(), |_| true, &online)
+    .await;
+    assert_eq!(res, Ok(1));
+    assert!(start.elapsed() >= Duration::from_secs(30));
+}
+
+#[tokio::test]
+async fn watch_waits_for_transition_to_true_after_min_delay() {
+    tokio::time::pause();
+    let (tx, rx) = watch::channel(false);
+    let start = Instant::now();
+    tokio::spawn(async move {
+        tokio::time::sleep(Duration::from_secs(1)).await;
+        tx.send_replace(true);
+        // keep the sender alive until the retry happened
+        tokio::time::sleep(Duration::from_secs(60)).await;
+    });
+    let gate = ReadyGate::new(rx).min_delay(Duration::from_secs(5));
+    let res = RetryPolicy {
+        attempts: 3,
+        base_delay: Duration::from_secs(30),
+        max_delay: Duration::from_secs(60),
+        jitter: false,
+        ..Default::default()
+    }
+    .retry_when_ready(fail_once(), |_| true, gate)
+    .await;
+    assert_eq!(res, Ok(1));
+    // the transition happened during the minimum delay, so the backoff bounds the wait
+    assert!(start.elapsed() >= Duration::from_secs(30));
+}
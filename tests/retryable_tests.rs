@@ -0,0 +1,171 @@
+use asyn_retry_policy::predicate::{RetryPredicate, is_retryable};
+use asyn_retry_policy::{IsRetryable, RetryPolicy, Retryable, retry};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::time::Duration;
+use tokio::time::Instant;
+
+#[derive(Debug, PartialEq, Retryable)]
+enum ConnectError {
+    #[retryable]
+    Refused,
+    #[fatal]
+    BadAddress,
+}
+
+#[derive(Debug, PartialEq, thiserror::Error, Retryable)]
+enum ApiError {
+    #[error("timed out")]
+    #[retryable]
+    Timeout,
+    #[error("throttled")]
+    #[retryable(after_ms = 2000)]
+    Throttled { quota: u32 },
+    #[error("not found")]
+    #[fatal]
+    NotFound(String),
+    #[error("connect failed")]
+    #[retryable(from_source)]
+    Connect(#[source] ConnectError, u16),
+}
+
+impl std::fmt::Display for ConnectError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{self:?}")
+    }
+}
+
+impl std::error::Error for ConnectError {}
+
+#[derive(Debug, PartialEq, Retryable)]
+enum QuotaError {
+    #[retryable(after_ms = 5000)]
+    Exhausted,
+    #[fatal]
+    Denied,
+}
+
+#[derive(Debug, Retryable)]
+#[retryable(default = retryable)]
+enum Flaky {
+    Sometimes,
+    #[fatal]
+    Never,
+}
+
+#[test]
+fn variants_are_classified() {
+    assert!(ApiError::Timeout.is_retryable());
+    assert_eq!(ApiError::Timeout.retry_after(), None);
+    assert!(ApiError::Throttled { quota: 0 }.is_retryable());
+    assert_eq!(
+        ApiError::Throttled { quota: 0 }.retry_after(),
+        Some(Duration::from_millis(2000))
+    );
+    assert!(!ApiError::NotFound("user".into()).is_retryable());
+    assert!(ApiError::Connect(ConnectError::Refused, 80).is_retryable());
+    assert!(!ApiError::Connect(ConnectError::BadAddress, 80).is_retryable());
+    assert!(Flaky::Sometimes.is_retryable());
+    assert!(!Flaky::Never.is_retryable());
+}
+
+#[tokio::test]
+async fn retry_classified_honors_the_classification_and_delay() {
+    tokio::time::pause();
+    let start = Instant::now();
+    let calls = AtomicUsize::new(0);
+    let res = RetryPolicy {
+        attempts: 4,
+        base_delay: Duration::from_millis(10),
+        jitter: false,
+        ..Default::default()
+    }
+    .retry_classified(|| {
+        let n = calls.fetch_add(1, Ordering::SeqCst);
+        async move {
+            Err::<(), _>(match n {
+                0 => ApiError::Throttled { quota: 0 },
+                _ => ApiError::NotFound("user".into()),
+            })
+        }
+    })
+    .await;
+    assert_eq!(res, Err(ApiError::NotFound("user".into())));
+    assert_eq!(calls.load(Ordering::SeqCst), 2);
+    // the throttled error's delay replaced the 10ms backoff
+    assert!(start.elapsed() >= Duration::from_millis(2000));
+}
+
+#[tokio::test]
+async fn classification_composes_with_combinators() {
+    tokio::time::pause();
+    let calls = AtomicUsize::new(0);
+    let res = RetryPolicy {
+        attempts: 4,
+        base_delay: Duration::from_millis(10),
+        jitter: false,
+        ..Default::default()
+    }
+    .retry_with(
+        || {
+            calls.fetch_add(1, Ordering::SeqCst);
+            async { Err::<(), _>(ApiError::Timeout) }
+        },
+        RetryPredicate::<ApiError>::max_times(IsRetryable, 1),
+    )
+    .await;
+    assert_eq!(res, Err(ApiError::Timeout));
+    assert_eq!(calls.load(Ordering::SeqCst), 2);
+}
+
+static MACRO_CALLS: AtomicUsize = AtomicUsize::new(0);
+
+#[retry(attempts = 5, base_delay_ms = 10)]
+async fn lookup() -> Result<u8, ApiError> {
+    let n = MACRO_CALLS.fetch_add(1, Ordering::SeqCst);
+    match n {
+        0 => Err(ApiError::Connect(ConnectError::Refused, 443)),
+        _ => Err(ApiError::NotFound("user".into())),
+    }
+}
+
+static FALLBACK_CALLS: AtomicUsize = AtomicUsize::new(0);
+
+#[retry(attempts = 5, base_delay_ms = 10, fallback = |_e, _stats| async { Ok(0) })]
+async fn lookup_or_default() -> Result<u8, ApiError> {
+    FALLBACK_CALLS.fetch_add(1, Ordering::SeqCst);
+    Err(ApiError::NotFound("user".into()))
+}
+
+#[tokio::test]
+async fn retry_macro_uses_the_classification_without_a_predicate() {
+    tokio::time::pause();
+    assert_eq!(lookup().await, Err(ApiError::NotFound("user".into())));
+    assert_eq!(MACRO_CALLS.load(Ordering::SeqCst), 2);
+
+    assert_eq!(lookup_or_default().await, Ok(0));
+    assert_eq!(FALLBACK_CALLS.load(Ordering::SeqCst), 1);
+}
+
+fn typed() -> impl RetryPredicate<QuotaError> {
+    is_retryable::<QuotaError>().max_times(5)
+}
+
+static QUOTA_CALLS: AtomicUsize = AtomicUsize::new(0);
+
+#[retry(attempts = 5, base_delay_ms = 10, jitter = false, predicate = typed())]
+async fn reserve() -> Result<u8, QuotaError> {
+    match QUOTA_CALLS.fetch_add(1, Ordering::SeqCst) {
+        0 => Err(QuotaError::Exhausted),
+        _ => Err(QuotaError::Denied),
+    }
+}
+
+#[tokio::test]
+async fn retry_macro_keeps_the_delay_of_a_composed_predicate() {
+    tokio::time::pause();
+    let start = Instant::now();
+    assert_eq!(reserve().await, Err(QuotaError::Denied));
+    assert_eq!(QUOTA_CALLS.load(Ordering::SeqCst), 2);
+    // `after_ms = 5000` replaced the 10ms backoff
+    assert!(start.elapsed() >= Duration::from_millis(5000));
+}
@@ -0,0 +1,139 @@
+use asyn_retry_policy::{RetryPolicy, Saga};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+type Log = Arc<Mutex<Vec<String>>>;
+
+/// Action that logs `label` and fails the first `failures` calls.
+fn action(
+    log: &Log,
+    label: &'static str,
+    failures: usize,
+) -> impl FnMut() -> std::future::Ready<Result<(), String>> + Send + 'static {
+    let log = log.clone();
+    let mut calls = 0;
+    move || {
+        calls += 1;
+        log.lock().unwrap().push(label.to_string());
+        std::future::ready(if calls <= failures {
+            Err(format!("{label} failed"))
+        } else {
+            Ok(())
+        })
+    }
+}
+
+#[tokio::test]
+async fn runs_all_steps_with_retries() {
+    let log = Log::default();
+    let res = Saga::new()
+        .step(
+            "reserve",
+            RetryPolicy {
+                attempts: 3,
+                base_delay: Duration::from_millis(1),
+                jitter: false,
+                ..Default::default()
+            },
+            action(&log, "reserve", 1),
+            action(&log, "release", 0),
+        )
+        .step(
+            "charge",
+            RetryPolicy {
+                attempts: 3,
+                base_delay: Duration::from_millis(1),
+                jitter: false,
+                ..Default::default()
+            },
+            action(&log, "charge", 0),
+            action(&log, "refund", 0),
+        )
+        .run()
+        .await;
+    assert!(res.is_ok());
+    assert_eq!(*log.lock().unwrap(), vec!["reserve", "reserve", "charge"]);
+}
+
+#[tokio::test]
+async fn failure_compensates_completed_steps_in_reverse() {
+    let log = Log::default();
+    let err = Saga::new()
+        .step(
+            "reserve",
+            RetryPolicy {
+                attempts: 3,
+                base_delay: Duration::from_millis(1),
+                jitter: false,
+                ..Default::default()
+            },
+            action(&log, "reserve", 0),
+            action(&log, "release", 0),
+        )
+        .step(
+            "charge",
+            RetryPolicy {
+                attempts: 3,
+                base_delay: Duration::from_millis(1),
+                jitter: false,
+                ..Default::default()
+            },
+            action(&log, "charge", 0),
+            action(&log, "refund", 5),
+        )
+        .step(
+            "ship",
+            RetryPolicy {
+                attempts: 2,
+                base_delay: Duration::from_millis(1),
+                jitter: false,
+                ..Default::default()
+            },
+            action(&log, "ship", 5),
+            action(&log, "recall", 0),
+        )
+        .run()
+        .await
+        .unwrap_err();
+
+    assert_eq!(err.failed_step, "ship");
+    assert_eq!(err.error, "ship failed");
+    let compensations: Vec<_> = err
+        .compensations
+        .iter()
+        .map(|c| (c.step, c.result.is_ok()))
+        .collect();
+    assert_eq!(compensations, vec![("charge", false), ("reserve", true)]);
+    assert!(!err.fully_compensated());
+    assert_eq!(
+        *log.lock().unwrap(),
+        vec![
+            "reserve", "charge", "ship", "ship", "refund", "refund", "refund", "release"
+        ]
+    );
+    assert_eq!(err.to_string(), "saga step `ship` failed: ship failed");
+}
+
+#[tokio::test]
+async fn retry_if_limits_retried_errors() {
+    let log = Log::default();
+    let err = Saga::new()
+        .retry_if(|e: &String| !e.starts_with("reserve"))
+        .step(
+            "reserve",
+            RetryPolicy {
+                attempts: 3,
+                base_delay: Duration::from_millis(1),
+                jitter: false,
+                ..Default::default()
+            },
+            action(&log, "reserve", 1),
+            action(&log, "release", 0),
+        )
+        .run()
+        .await
+        .unwrap_err();
+    assert_eq!(err.failed_step, "reserve");
+    assert!(err.compensations.is_empty());
+    assert_eq!(*log.lock().unwrap(), vec!["reserve"]);
+}
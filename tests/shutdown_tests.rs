@@ -0,0 +1,228 @@
+use asyn_retry_policy::{
+    CancelToken, RetryError, RetryPolicy, ShutdownCoordinator, ShutdownReport, retry,
+};
+use std::sync::atomic::AtomicBool;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, LazyLock};
+use std::time::Duration;
+use tokio::time::Instant;
+
+/// Fails the first `failures` calls, then succeeds with the call number.
+fn flaky(
+    failures: usize,
+) -> impl FnMut() -> std::future::Ready<Result<usize, String>> + Send + 'static {
+    let calls = Arc::new(AtomicUsize::new(0));
+    move || {
+        let n = calls.fetch_add(1, Ordering::SeqCst) + 1;
+        std::future::ready(if n > failures {
+            Ok(n)
+        } else {
+            Err(format!("failure {n}"))
+        })
+    }
+}
+
+#[tokio::test]
+async fn drain_skips_remaining_backoff() {
+    tokio::time::pause();
+    let coordinator = ShutdownCoordinator::new();
+    let policy = coordinator.register(RetryPolicy {
+        attempts: 5,
+        base_delay: Duration::from_secs(10),
+        max_delay: Duration::from_secs(60),
+        jitter: false,
+        ..Default::default()
+    });
+    let start = Instant::now();
+    let task = tokio::spawn(async move { policy.retry(flaky(3), |_| true).await });
+
+    tokio::time::sleep(Duration::from_secs(1)).await;
+    assert_eq!(coordinator.active(), 1);
+    let report = coordinator.drain(Duration::from_secs(30)).await;
+
+    assert_eq!(
+        report,
+        ShutdownReport {
+            interrupted: 0,
+            completed: 1,
+            still_running: 0,
+        }
+    );
+    assert_eq!(task.await.unwrap(), Ok(4));
+    assert!(start.elapsed() < Duration::from_secs(2));
+    assert!(coordinator.is_shutting_down());
+}
+
+#[tokio::test]
+async fn fail_fast_interrupts_with_last_error() {
+    tokio::time::pause();
+    let coordinator = ShutdownCoordinator::new();
+    let plain = coordinator.register(RetryPolicy {
+        attempts: 5,
+        base_delay: Duration::from_secs(10),
+        max_delay: Duration::from_secs(60),
+        jitter: false,
+        ..Default::default()
+    });
+    let reporting = coordinator.register(RetryPolicy {
+        attempts: 5,
+        base_delay: Duration::from_secs(10),
+        max_delay: Duration::from_secs(60),
+        jitter: false,
+        ..Default::default()
+    });
+    let plain = tokio::spawn(async move { plain.retry(flaky(usize::MAX), |_| true).await });
+    let reporting = tokio::spawn(async move {
+        reporting
+            .retry_with_cancel(flaky(usize::MAX), |_| true, &CancelToken::new())
+            .await
+    });
+
+    tokio::time::sleep(Duration::from_secs(15)).await;
+    let report = coordinator.fail_fast(Duration::from_secs(5)).await;
+
+    assert_eq!(report.interrupted, 2);
+    assert_eq!(report.still_running, 0);
+    assert_eq!(plain.await.unwrap(), Err("failure 2".to_string()));
+    match reporting.await.unwrap() {
+        Err(RetryError::ShuttingDown { last_error, stats }) => {
+            assert_eq!(last_error, "failure 2");
+            assert_eq!(stats.attempts, 2);
+        }
+        other => panic!("expected shutdown, got {other:?}"),
+    }
+}
+
+#[tokio::test]
+async fn drain_reports_attempts_still_running_after_grace() {
+    tokio::time::pause();
+    let coordinator = ShutdownCoordinator::new();
+    let policy = coordinator.register(RetryPolicy {
+        attempts: 5,
+        base_delay: Duration::from_secs(10),
+        max_delay: Duration::from_secs(60),
+        jitter: false,
+        ..Default::default()
+    });
+    let task = tokio::spawn(async move {
+        policy
+            .retry(
+                || async {
+                    tokio::time::sleep(Duration::from_secs(20)).await;
+                    Err::<(), _>("slow failure")
+                },
+                |_| true,
+            )
+            .await
+    });
+
+    tokio::time::sleep(Duration::from_secs(1)).await;
+    let report = coordinator.drain(Duration::from_secs(5)).await;
+    assert_eq!(report.still_running, 1);
+
+    // the grace period is over, so the sequence stops at its next backoff
+    assert_eq!(task.await.unwrap(), Err("slow failure"));
+    assert_eq!(coordinator.active(), 0);
+    assert_eq!(coordinator.fail_fast(Duration::ZERO).await.interrupted, 1);
+}
+
+#[tokio::test]
+async fn unregistered_policies_are_unaffected() {
+    tokio::time::pause();
+    let coordinator = ShutdownCoordinator::new();
+    let policy = RetryPolicy {
+        attempts: 5,
+        base_delay: Duration::from_secs(10),
+        max_delay: Duration::from_secs(60),
+        jitter: false,
+        ..Default::default()
+    };
+    let start = Instant::now();
+    let task = tokio::spawn(async move { policy.retry(flaky(1), |_| true).await });
+    tokio::time::sleep(Duration::from_secs(1)).await;
+
+    assert_eq!(
+        coordinator.fail_fast(Duration::ZERO).await,
+        ShutdownReport::default()
+    );
+    assert_eq!(task.await.unwrap(), Ok(2));
+    assert!(start.elapsed() >= Duration::from_secs(10));
+}
+
+#[tokio::test]
+async fn retry_until_shutdown_reports_the_interruption() {
+    tokio::time::pause();
+    let coordinator = ShutdownCoordinator::new();
+    let policy = coordinator.register(RetryPolicy {
+        attempts: 5,
+        base_delay: Duration::from_secs(10),
+        max_delay: Duration::from_secs(60),
+        jitter: false,
+        ..Default::default()
+    });
+    assert!(matches!(
+        policy.retry_until_shutdown(flaky(usize::MAX), |_| false).await,
+        Err(RetryError::Failed(e)) if e == "failure 1"
+    ));
+    assert_eq!(
+        policy
+            .retry_until_shutdown(flaky(1), |_| true)
+            .await
+            .unwrap(),
+        2
+    );
+
+    let task = tokio::spawn(async move {
+        policy
+            .retry_until_shutdown(flaky(usize::MAX), |_| true)
+            .await
+    });
+    tokio::time::sleep(Duration::from_secs(15)).await;
+    coordinator.fail_fast(Duration::from_secs(5)).await;
+    match task.await.unwrap() {
+        Err(RetryError::ShuttingDown { last_error, stats }) => {
+            assert_eq!(last_error, "failure 2");
+            assert_eq!(stats.attempts, 2);
+        }
+        other => panic!("expected shutdown, got {other:?}"),
+    }
+}
+
+static MACRO_SHUTDOWN: LazyLock<ShutdownCoordinator> = LazyLock::new(ShutdownCoordinator::new);
+
+static FAIL: AtomicBool = AtomicBool::new(true);
+
+#[retry(
+    until_shutdown,
+    policy = MACRO_SHUTDOWN.register(RetryPolicy::default()),
+    attempts = 5,
+    base_delay_ms = 10000,
+    max_delay_ms = 60000,
+    jitter = false
+)]
+async fn deliver(id: u32) -> Result<u32, RetryError<String>> {
+    if FAIL.load(Ordering::SeqCst) {
+        Err(format!("delivery {id} failed"))
+    } else {
+        Ok(id)
+    }
+}
+
+#[tokio::test]
+async fn retry_macro_can_report_shutdown() {
+    tokio::time::pause();
+    FAIL.store(false, Ordering::SeqCst);
+    assert_eq!(deliver(1).await.unwrap(), 1);
+
+    FAIL.store(true, Ordering::SeqCst);
+    let task = tokio::spawn(deliver(2));
+    tokio::time::sleep(Duration::from_secs(15)).await;
+    MACRO_SHUTDOWN.fail_fast(Duration::from_secs(5)).await;
+    match task.await.unwrap() {
+        Err(RetryError::ShuttingDown { last_error, stats }) => {
+            assert_eq!(last_error, "delivery 2 failed");
+            assert_eq!(stats.attempts, 2);
+        }
+        other => panic!("expected shutdown, got {other:?}"),
+    }
+}
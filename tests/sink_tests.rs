@@ -0,0 +1,232 @@
+use asyn_retry_policy::{RetryPolicy, RetrySink};
+use futures::{Sink, SinkExt};
+use std::pin::Pin;
+use std::sync::{Arc, Mutex};
+use std::task::{Context, Poll};
+use std::time::Duration;
+
+/// Test sink that fails a configurable number of `poll_ready`/`start_send`/`poll_flush` calls.
+#[derive(Default)]
+struct Flaky {
+    ready_failures: Arc<Mutex<usize>>,
+    send_failures: Arc<Mutex<usize>>,
+    flush_failures: Arc<Mutex<usize>>,
+    staged: Vec<u32>,
+    delivered: Arc<Mutex<Vec<u32>>>,
+}
+
+fn take_failure(counter: &Mutex<usize>) -> bool {
+    let mut left = counter.lock().unwrap();
+    let fail = *left > 0;
+    *left = left.saturating_sub(1);
+    fail
+}
+
+impl Sink<u32> for Flaky {
+    type Error = &'static str;
+
+    fn poll_ready(self: Pin<&mut Self>, _: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        if take_failure(&self.ready_failures) {
+            Poll::Ready(Err("not ready"))
+        } else {
+            Poll::Ready(Ok(()))
+        }
+    }
+
+    fn start_send(self: Pin<&mut Self>, item: u32) -> Result<(), Self::Error> {
+        if take_failure(&self.send_failures) {
+            return Err("rejected");
+        }
+        self.get_mut().staged.push(item);
+        Ok(())
+    }
+
+    fn poll_flush(self: Pin<&mut Self>, _: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        let this = self.get_mut();
+        if take_failure(&this.flush_failures) {
+            // a failed flush loses whatever was staged
+            this.staged.clear();
+            return Poll::Ready(Err("flush failed"));
+        }
+        this.delivered.lock().unwrap().append(&mut this.staged);
+        Poll::Ready(Ok(()))
+    }
+
+    fn poll_close(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.poll_flush(cx)
+    }
+}
+
+#[tokio::test]
+async fn retries_failed_poll_ready() {
+    tokio::time::pause();
+    let inner = Flaky::default();
+    *inner.ready_failures.lock().unwrap() = 2;
+    let delivered = inner.delivered.clone();
+
+    let mut sink = RetrySink::new(
+        RetryPolicy {
+            attempts: 3,
+            base_delay: Duration::from_millis(10),
+            jitter: false,
+            ..Default::default()
+        },
+        inner,
+    );
+    sink.send(1).await.unwrap();
+    sink.send(2).await.unwrap();
+    assert_eq!(*delivered.lock().unwrap(), vec![1, 2]);
+}
+
+#[tokio::test]
+async fn surfaces_error_once_exhausted() {
+    tokio::time::pause();
+    let inner = Flaky::default();
+    *inner.ready_failures.lock().unwrap() = 5;
+
+    let mut sink = RetrySink::new(
+        RetryPolicy {
+            attempts: 3,
+            base_delay: Duration::from_millis(10),
+            jitter: false,
+            ..Default::default()
+        },
+        inner,
+    );
+    assert_eq!(sink.send(1).await, Err("not ready"));
+}
+
+#[tokio::test]
+async fn cloned_items_survive_failed_flush_on_reconnect() {
+    tokio::time::pause();
+    let delivered = Arc::new(Mutex::new(Vec::new()));
+    let flush_failures = Arc::new(Mutex::new(1));
+    let connects = Arc::new(Mutex::new(0));
+
+    let mut sink = RetrySink::reconnecting(
+        RetryPolicy {
+            attempts: 3,
+            base_delay: Duration::from_millis(10),
+            jitter: false,
+            ..Default::default()
+        },
+        {
+            let delivered = delivered.clone();
+            let connects = connects.clone();
+            move || {
+                *connects.lock().unwrap() += 1;
+                Flaky {
+                    flush_failures: flush_failures.clone(),
+                    delivered: delivered.clone(),
+                    ..Default::default()
+                }
+            }
+        },
+    )
+    .clone_items();
+    sink.feed(1).await.unwrap();
+    sink.feed(2).await.unwrap();
+    sink.flush().await.unwrap();
+    assert_eq!(*delivered.lock().unwrap(), vec![1, 2]);
+    assert_eq!(*connects.lock().unwrap(), 2);
+}
+
+#[tokio::test]
+async fn buffered_items_are_lost_by_failed_flush() {
+    tokio::time::pause();
+    let inner = Flaky::default();
+    *inner.flush_failures.lock().unwrap() = 1;
+    let delivered = inner.delivered.clone();
+
+    let mut sink = RetrySink::new(
+        RetryPolicy {
+            attempts: 3,
+            base_delay: Duration::from_millis(10),
+            jitter: false,
+            ..Default::default()
+        },
+        inner,
+    );
+    sink.feed(1).await.unwrap();
+    sink.flush().await.unwrap();
+    sink.send(2).await.unwrap();
+    assert_eq!(*delivered.lock().unwrap(), vec![2]);
+}
+
+#[tokio::test]
+async fn rejected_items_are_resent() {
+    tokio::time::pause();
+    let policy = RetryPolicy {
+        attempts: 3,
+        base_delay: Duration::from_millis(10),
+        jitter: false,
+        ..Default::default()
+    };
+
+    let inner = Flaky::default();
+    *inner.send_failures.lock().unwrap() = 2;
+    let delivered = inner.delivered.clone();
+    let mut sink = RetrySink::new(policy.clone(), inner);
+    sink.send(1).await.unwrap();
+    sink.send(2).await.unwrap();
+    assert_eq!(*delivered.lock().unwrap(), vec![1, 2]);
+
+    let inner = Flaky::default();
+    *inner.send_failures.lock().unwrap() = 2;
+    let delivered = inner.delivered.clone();
+    let mut sink = RetrySink::new(policy, inner).clone_items();
+    sink.send(1).await.unwrap();
+    sink.send(2).await.unwrap();
+    assert_eq!(*delivered.lock().unwrap(), vec![1, 2]);
+}
+
+#[tokio::test]
+async fn failing_flush_is_surfaced_once_exhausted() {
+    tokio::time::pause();
+    let inner = Flaky::default();
+    *inner.flush_failures.lock().unwrap() = usize::MAX;
+    let flush_failures = inner.flush_failures.clone();
+
+    let mut sink = RetrySink::new(
+        RetryPolicy {
+            attempts: 3,
+            base_delay: Duration::from_millis(10),
+            jitter: false,
+            ..Default::default()
+        },
+        inner,
+    )
+    .clone_items();
+    assert_eq!(sink.send(1).await, Err("flush failed"));
+    assert_eq!(usize::MAX - *flush_failures.lock().unwrap(), 3);
+
+    // the unflushed item stays buffered instead of being dropped
+    assert_eq!(sink.pending(), 1);
+    assert_eq!(sink.take_pending(), vec![1]);
+    assert_eq!(sink.pending(), 0);
+}
+
+#[tokio::test]
+async fn undelivered_items_are_kept_when_exhausted() {
+    tokio::time::pause();
+    let inner = Flaky::default();
+    *inner.ready_failures.lock().unwrap() = 3;
+    let delivered = inner.delivered.clone();
+
+    let mut sink = RetrySink::new(
+        RetryPolicy {
+            attempts: 3,
+            base_delay: Duration::from_millis(10),
+            jitter: false,
+            ..Default::default()
+        },
+        inner,
+    );
+    sink.start_send_unpin(1).unwrap();
+    sink.start_send_unpin(2).unwrap();
+    assert_eq!(sink.flush().await, Err("not ready"));
+    assert_eq!(sink.pending(), 2);
+    // the next flush starts over with a fresh retry count
+    sink.flush().await.unwrap();
+    assert_eq!(*delivered.lock().unwrap(), vec![1, 2]);
+}
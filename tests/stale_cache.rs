@@ -0,0 +1,103 @@
+use asyn_retry_policy::{Freshness, RetryPolicy, StaleCache};
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, AtomicU8, Ordering};
+use std::time::Duration;
+
+async fn fetch(
+    cache: &StaleCache<&'static str, u8>,
+    up: Arc<AtomicBool>,
+    tries: Arc<AtomicU8>,
+) -> Result<asyn_retry_policy::Cached<u8>, &'static str> {
+    cache
+        .get(
+            "key",
+            &RetryPolicy {
+                attempts: 2,
+                base_delay: Duration::from_millis(1),
+                jitter: false,
+                ..Default::default()
+            },
+            move || {
+                let up = up.clone();
+                let tries = tries.clone();
+                async move {
+                    let n = tries.fetch_add(1, Ordering::SeqCst);
+                    if up.load(Ordering::SeqCst) {
+                        Ok(n)
+                    } else {
+                        Err("down")
+                    }
+                }
+            },
+            |_| true,
+        )
+        .await
+}
+
+#[tokio::test]
+async fn serves_stale_value_when_retries_exhausted() {
+    tokio::time::pause();
+    let cache = StaleCache::new(Duration::from_secs(1), Duration::from_secs(60), 8);
+    let up = Arc::new(AtomicBool::new(true));
+    let tries = Arc::new(AtomicU8::new(0));
+
+    let first = fetch(&cache, up.clone(), tries.clone()).await.unwrap();
+    assert_eq!(first.value, 0);
+    assert_eq!(first.freshness, Freshness::Fresh);
+
+    // within the TTL the cached value is served without fetching
+    let cached = fetch(&cache, up.clone(), tries.clone()).await.unwrap();
+    assert_eq!(cached.value, 0);
+    assert!(!cached.is_stale());
+    assert_eq!(tries.load(Ordering::SeqCst), 1);
+
+    tokio::time::advance(Duration::from_secs(5)).await;
+    up.store(false, Ordering::SeqCst);
+    let stale = fetch(&cache, up.clone(), tries.clone()).await.unwrap();
+    assert_eq!(stale.value, 0);
+    assert!(stale.is_stale());
+    assert!(stale.age >= Duration::from_secs(5));
+    assert_eq!(tries.load(Ordering::SeqCst), 3);
+}
+
+#[tokio::test]
+async fn errors_once_value_exceeds_max_staleness() {
+    tokio::time::pause();
+    let cache = StaleCache::new(Duration::ZERO, Duration::from_secs(10), 8);
+    let up = Arc::new(AtomicBool::new(true));
+    let tries = Arc::new(AtomicU8::new(0));
+
+    fetch(&cache, up.clone(), tries.clone()).await.unwrap();
+    up.store(false, Ordering::SeqCst);
+    tokio::time::advance(Duration::from_secs(11)).await;
+
+    let res = fetch(&cache, up, tries).await;
+    assert_eq!(res.unwrap_err(), "down");
+}
+
+#[tokio::test]
+async fn evicts_oldest_entry_when_full() {
+    tokio::time::pause();
+    let cache = StaleCache::new(Duration::from_secs(1), Duration::from_secs(60), 2);
+    cache.insert(1, "a");
+    tokio::time::advance(Duration::from_millis(10)).await;
+    cache.insert(2, "b");
+    tokio::time::advance(Duration::from_millis(10)).await;
+    cache.insert(3, "c");
+    assert_eq!(cache.len(), 2);
+
+    let res = cache
+        .get(
+            1,
+            &RetryPolicy {
+                attempts: 2,
+                base_delay: Duration::from_millis(1),
+                jitter: false,
+                ..Default::default()
+            },
+            || async { Err::<&str, _>("down") },
+            |_| true,
+        )
+        .await;
+    assert!(res.is_err());
+}
@@ -0,0 +1,144 @@
+use asyn_retry_policy::{RetryPolicy, retry_stream};
+use futures::StreamExt;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+#[tokio::test]
+async fn resubscribes_from_last_token() {
+    tokio::time::pause();
+    let subscriptions = Arc::new(Mutex::new(Vec::new()));
+    let seen = subscriptions.clone();
+    let stream = retry_stream(
+        RetryPolicy {
+            attempts: 3,
+            base_delay: Duration::from_millis(10),
+            jitter: false,
+            ..Default::default()
+        },
+        move |token: Option<u32>| {
+            seen.lock().unwrap().push(token);
+            let start = token.map_or(0, |t| t + 1);
+            // every subscription yields two items and then breaks, until item 5
+            let items: Vec<Result<u32, &str>> = (start..start + 2)
+                .map(Ok)
+                .chain((start + 2 <= 5).then_some(Err("broken")))
+                .collect();
+            futures::stream::iter(items)
+        },
+        |item: &u32| *item,
+        |_| true,
+    );
+    let items: Vec<_> = stream.collect().await;
+    assert_eq!(items, (0..6).map(Ok).collect::<Vec<_>>());
+    assert_eq!(*subscriptions.lock().unwrap(), vec![None, Some(1), Some(3)]);
+}
+
+#[tokio::test]
+async fn ends_with_error_once_exhausted() {
+    tokio::time::pause();
+    let stream = retry_stream(
+        RetryPolicy {
+            attempts: 3,
+            base_delay: Duration::from_millis(10),
+            jitter: false,
+            ..Default::default()
+        },
+        |_token: Option<u32>| futures::stream::iter(vec![Err::<u32, _>("down")]),
+        |item: &u32| *item,
+        |_| true,
+    );
+    let items: Vec<_> = stream.collect().await;
+    assert_eq!(items, vec![Err("down")]);
+}
+
+#[tokio::test]
+async fn non_retryable_error_ends_stream() {
+    let stream = retry_stream(
+        RetryPolicy {
+            attempts: 3,
+            base_delay: Duration::from_millis(10),
+            jitter: false,
+            ..Default::default()
+        },
+        |_token: Option<u32>| futures::stream::iter(vec![Ok(1), Err("fatal"), Ok(2)]),
+        |item: &u32| *item,
+        |_| false,
+    );
+    let items: Vec<_> = stream.collect().await;
+    assert_eq!(items, vec![Ok(1), Err("fatal")]);
+}
+
+#[tokio::test]
+async fn retry_each_retries_items_independently() {
+    use asyn_retry_policy::RetryStreamExt;
+    use std::collections::HashMap;
+
+    tokio::time::pause();
+    let failures = Arc::new(Mutex::new(HashMap::from([(2u32, 1usize), (3, 5)])));
+    let source = futures::stream::iter(vec![Ok(1u32), Ok(2), Ok(3), Err("bad input"), Ok(4)]);
+    let outcomes: Vec<_> = source
+        .retry_each_ordered(
+            RetryPolicy {
+                attempts: 3,
+                base_delay: Duration::from_millis(10),
+                jitter: false,
+                ..Default::default()
+            },
+            2,
+            move |item| {
+                let failures = failures.clone();
+                async move {
+                    let mut failures = failures.lock().unwrap();
+                    match failures.get_mut(&item) {
+                        Some(left) if *left > 0 => {
+                            *left -= 1;
+                            Err("flaky")
+                        }
+                        _ => Ok(item * 10),
+                    }
+                }
+            },
+            |_| true,
+        )
+        .collect()
+        .await;
+
+    let summary: Vec<_> = outcomes
+        .iter()
+        .map(|o| (o.result, o.stats.attempts))
+        .collect();
+    assert_eq!(
+        summary,
+        vec![
+            (Ok(10), 1),
+            (Ok(20), 2),
+            (Err("flaky"), 3),
+            (Err("bad input"), 0),
+            (Ok(40), 1),
+        ]
+    );
+}
+
+#[tokio::test]
+async fn retry_each_unordered_yields_every_item() {
+    use asyn_retry_policy::RetryStreamExt;
+
+    let source = futures::stream::iter((0..10u32).map(Ok::<_, &str>));
+    let mut values: Vec<_> = source
+        .retry_each(
+            RetryPolicy {
+                attempts: 3,
+                base_delay: Duration::from_millis(10),
+                jitter: false,
+                ..Default::default()
+            },
+            4,
+            |item| async move { Ok(item) },
+            |_| true,
+        )
+        .map(|o| o.result.unwrap())
+        .collect()
+        .await;
+    values.sort();
+    assert_eq!(values, (0..10).collect::<Vec<_>>());
+}
@@ -0,0 +1,124 @@
+use asyn_retry_policy::{RetryPolicy, StopReason, Supervisor, supervise};
+use std::sync::Arc;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::time::Duration;
+
+#[tokio::test]
+async fn restarts_until_policy_exhausted() {
+    tokio::time::pause();
+    let runs = Arc::new(AtomicUsize::new(0));
+    let report = supervise(
+        RetryPolicy {
+            attempts: 3,
+            base_delay: Duration::from_millis(10),
+            jitter: false,
+            ..Default::default()
+        },
+        || {
+            let runs = runs.clone();
+            async move {
+                runs.fetch_add(1, Ordering::SeqCst);
+                Err::<(), _>("crashed")
+            }
+        },
+    )
+    .await;
+    assert_eq!(report.reason, StopReason::Exhausted);
+    assert_eq!(report.restarts, 2);
+    assert_eq!(report.last_exit, Some(Err("crashed")));
+    assert_eq!(runs.load(Ordering::SeqCst), 3);
+}
+
+#[tokio::test]
+async fn healthy_runs_reset_the_backoff() {
+    tokio::time::pause();
+    let runs = Arc::new(AtomicUsize::new(0));
+    let report = Supervisor::new(RetryPolicy {
+        attempts: 2,
+        base_delay: Duration::from_millis(10),
+        jitter: false,
+        ..Default::default()
+    })
+    .min_uptime(Duration::from_secs(1))
+    .run(
+        || {
+            let runs = runs.clone();
+            async move {
+                // the first four runs stay up long enough to count as healthy
+                if runs.fetch_add(1, Ordering::SeqCst) < 4 {
+                    tokio::time::sleep(Duration::from_secs(2)).await;
+                }
+            }
+        },
+        std::future::pending(),
+    )
+    .await;
+    assert_eq!(report.reason, StopReason::Exhausted);
+    assert_eq!(runs.load(Ordering::SeqCst), 5);
+}
+
+#[tokio::test]
+async fn restart_intensity_escalates() {
+    tokio::time::pause();
+    let report = Supervisor::new(RetryPolicy {
+        attempts: usize::MAX,
+        base_delay: Duration::from_millis(10),
+        jitter: false,
+        ..Default::default()
+    })
+    .restart_intensity(3, Duration::from_secs(10))
+    .run(|| async {}, std::future::pending())
+    .await;
+    assert_eq!(report.reason, StopReason::IntensityExceeded);
+    assert_eq!(report.restarts, 3);
+}
+
+#[tokio::test]
+async fn stops_on_shutdown_signal() {
+    tokio::time::pause();
+    let (tx, rx) = tokio::sync::oneshot::channel::<()>();
+    let handle = tokio::spawn(async move {
+        Supervisor::new(RetryPolicy {
+            attempts: usize::MAX,
+            base_delay: Duration::from_millis(10),
+            jitter: false,
+            ..Default::default()
+        })
+        .run(|| tokio::time::sleep(Duration::from_secs(1)), async {
+            let _ = rx.await;
+        })
+        .await
+    });
+    tokio::time::sleep(Duration::from_millis(2500)).await;
+    tx.send(()).unwrap();
+    let report = handle.await.unwrap();
+    assert_eq!(report.reason, StopReason::Shutdown);
+    assert_eq!(report.restarts, 2);
+}
+
+#[tokio::test]
+async fn restarts_indefinitely_at_max_delay() {
+    tokio::time::pause();
+    let runs = Arc::new(AtomicUsize::new(0));
+    let report = Supervisor::new(RetryPolicy {
+        attempts: usize::MAX,
+        base_delay: Duration::from_millis(10),
+        max_delay: Duration::from_secs(1),
+        jitter: false,
+        ..Default::default()
+    })
+    .run(
+        || {
+            let runs = runs.clone();
+            async move {
+                runs.fetch_add(1, Ordering::SeqCst);
+            }
+        },
+        tokio::time::sleep(Duration::from_secs(300)),
+    )
+    .await;
+    assert_eq!(report.reason, StopReason::Shutdown);
+    // hundreds of consecutive crashes, capped at one restart per second
+    assert!(report.restarts > 250);
+    assert_eq!(runs.load(Ordering::SeqCst), report.restarts + 1);
+}